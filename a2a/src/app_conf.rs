@@ -119,6 +119,14 @@ pub struct Serve {
   #[clap(long, default_value = "/mcp")]
   pub mcp_path: Option<String>,
 
+  /// secret to sign the local file links of FileAction PRESIGN, enables the `/files/...` route
+  #[clap(long, env = "A2A_FILES_SECRET")]
+  pub files_secret: Option<String>,
+
+  /// public base url of the server in the signed file links, default to `http://{listen}`
+  #[clap(long, env = "A2A_PUBLIC_URL")]
+  pub public_url: Option<String>,
+
   #[clap(skip)]
   pub conf_dir_path: PathBuf,
 
@@ -1,6 +1,7 @@
 mod chat_stream;
 mod mcps;
 mod openai;
+mod schema;
 
 use std::{
   path::{Path, PathBuf},
@@ -10,6 +11,7 @@ use std::{
 use crate::{
   app_conf::{Coder, Runner},
   coder::openai::ChatCompletionUsage,
+  config_loader::load_conf_dir,
   default_work_dir, run,
 };
 
@@ -26,6 +28,7 @@ use tokio::task::JoinSet;
 use tracing::{debug, info, trace, warn};
 
 use chat_stream::ChatStream;
+pub(crate) use schema::database_schema_prompt;
 
 pub const DEFAULT_SYSTEM_PROMPT: &'static str = include_str!("../code.md");
 pub const DEFAULT_API_DEFINE: &'static str = include_str!("../../../bindings/nodejs/action.ts");
@@ -85,7 +88,7 @@ pub(crate) fn default_system_prompt() -> &'static str {
 }
 
 pub(crate) async fn execute(arg: &Coder) -> Result<()> {
-  let system = arg
+  let mut system = arg
     .system
     .as_ref()
     .map(|s| text_or_file(&s))
@@ -100,6 +103,17 @@ pub(crate) async fn execute(arg: &Coder) -> Result<()> {
 
   let (user, is_file) = text_or_file(&arg.user);
 
+  let work_dir = arg
+    .work_dir
+    .as_ref()
+    .map(|p| PathBuf::from(p))
+    .unwrap_or(default_work_dir());
+  if let Ok(conf) = load_conf_dir(&work_dir.join("conf")) {
+    if let Some(schema) = database_schema_prompt(&conf, &user).await {
+      system.push_str(&schema);
+    }
+  }
+
   let default_output_name = if is_file {
     arg
       .user
@@ -157,11 +171,6 @@ pub(crate) async fn execute(arg: &Coder) -> Result<()> {
   if arg.run.unwrap_or(false) {
     for r in results.iter_mut() {
       if let Some(output) = r.output.as_ref() {
-        let work_dir = arg
-          .work_dir
-          .as_ref()
-          .map(|p| PathBuf::from(p))
-          .unwrap_or(default_work_dir());
         let runner = Runner {
           file: output.clone(),
           clean: arg.clean.clone(),
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use a2a_core::do_action;
+use a2a_types::{Action, SqlAction, Value};
+use tracing::{debug, warn};
+
+/// the connection string schemes of the sql action, except `files://`, whose tables are only
+/// loaded from the files referenced by the query
+const SQL_SCHEMES: &[&str] = &[
+  "mysql://",
+  "my://",
+  "postgres://",
+  "postgresql://",
+  "pgsql://",
+  "pg://",
+  "sqlite://",
+  "mssql://",
+  "sqlserver://",
+  "clickhouse://",
+  "ch://",
+];
+
+const SCHEMA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the schemas of the configured databases mentioned by the user prompt, appended to the system prompt,
+/// eg: `count the orders in mydb` mentions `config.mydb`
+pub(crate) async fn database_schema_prompt(conf: &Value, user: &str) -> Option<String> {
+  let mut connections = vec![];
+  sql_connections(conf, "config", &mut connections);
+
+  let user = user.to_lowercase();
+  let mut prompt = String::new();
+  for (path, connection) in connections {
+    let key = path.rsplit('.').next().unwrap_or_default().to_lowercase();
+    if !mentions(&user, &key) && !mentions(&user, &path.to_lowercase()) {
+      continue;
+    }
+
+    let action = Action::Sql(SqlAction {
+      connection: connection.to_string(),
+      method: Some("schema".to_string()),
+      ..Default::default()
+    });
+    let schema = match tokio::time::timeout(SCHEMA_TIMEOUT, do_action(action)).await {
+      Ok(Ok(schema)) => schema,
+      Ok(Err(err)) => {
+        warn!(%path, %err, "fetch database schema");
+        continue;
+      }
+      Err(_) => {
+        warn!(%path, "fetch database schema timeout");
+        continue;
+      }
+    };
+    debug!(%path, "database schema");
+    prompt.push_str(&format!("### {}\n", path));
+    prompt.push_str(&tables_prompt(&schema));
+  }
+
+  if prompt.is_empty() {
+    None
+  } else {
+    Some(format!(
+      "## Database Schema\nThe tables of the databases mentioned by the user, use the connection in `config`:\n{}",
+      prompt
+    ))
+  }
+}
+
+/// whether the text has the word, eg: `db` is in `query the db` but not in `mongodb`
+fn mentions(text: &str, word: &str) -> bool {
+  let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+  !word.is_empty()
+    && text.match_indices(word).any(|(i, _)| {
+      !text[..i].chars().next_back().is_some_and(is_word_char)
+        && !text[i + word.len()..]
+          .chars()
+          .next()
+          .is_some_and(is_word_char)
+    })
+}
+
+/// the config paths and the sql connection strings, eg: `config.mydb`
+fn sql_connections<'a>(conf: &'a Value, path: &str, result: &mut Vec<(String, &'a str)>) {
+  match conf {
+    Value::String(s) if SQL_SCHEMES.iter().any(|scheme| s.starts_with(scheme)) => {
+      result.push((path.to_string(), s.as_str()))
+    }
+    Value::Object(m) => m
+      .iter()
+      .for_each(|(k, v)| sql_connections(v, &format!("{}.{}", path, k), result)),
+    _ => {}
+  }
+}
+
+/// one line per table, eg: `- orders (~10 rows): id INTEGER PK, user_id INTEGER -> users.id`
+fn tables_prompt(schema: &Value) -> String {
+  let mut prompt = String::new();
+  let tables = schema["tables"]
+    .as_array()
+    .map(Vec::as_slice)
+    .unwrap_or_default();
+  for table in tables {
+    let name = match table["schema"].as_str() {
+      Some(schema) => format!("{}.{}", schema, table["name"].as_str().unwrap_or_default()),
+      None => table["name"].as_str().unwrap_or_default().to_string(),
+    };
+    let mut notes = vec![];
+    if table["type"] == "view" {
+      notes.push("view".to_string());
+    }
+    if let Some(rows) = table["rows"].as_i64() {
+      notes.push(format!("~{} rows", rows));
+    }
+    if let Some(comment) = table["comment"].as_str() {
+      notes.push(comment.to_string());
+    }
+
+    let columns = table["columns"]
+      .as_array()
+      .map(Vec::as_slice)
+      .unwrap_or_default()
+      .iter()
+      .map(|c| column_prompt(table, c))
+      .collect::<Vec<_>>();
+
+    if notes.is_empty() {
+      prompt.push_str(&format!("- {}: {}\n", name, columns.join(", ")));
+    } else {
+      prompt.push_str(&format!(
+        "- {} ({}): {}\n",
+        name,
+        notes.join(", "),
+        columns.join(", ")
+      ));
+    }
+  }
+  prompt
+}
+
+fn column_prompt(table: &Value, column: &Value) -> String {
+  let name = &column["name"];
+  let mut result = format!(
+    "{} {}",
+    name.as_str().unwrap_or_default(),
+    column["type"].as_str().unwrap_or_default()
+  );
+  if table["primaryKey"]
+    .as_array()
+    .is_some_and(|pk| pk.contains(name))
+  {
+    result.push_str(" PK");
+  }
+  if column["nullable"] == false {
+    result.push_str(" NOT NULL");
+  }
+  let foreign_keys = table["foreignKeys"]
+    .as_array()
+    .map(Vec::as_slice)
+    .unwrap_or_default();
+  for fk in foreign_keys {
+    let columns = fk["columns"]
+      .as_array()
+      .map(Vec::as_slice)
+      .unwrap_or_default();
+    if let Some(i) = columns.iter().position(|c| c == name) {
+      result.push_str(&format!(
+        " -> {}.{}",
+        fk["refTable"].as_str().unwrap_or_default(),
+        fk["refColumns"][i].as_str().unwrap_or_default()
+      ));
+    }
+  }
+  result
+}
@@ -0,0 +1,34 @@
+use axum::{
+  extract::{Path, Query, Request},
+  response::{IntoResponse, Response},
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use tower_http::services::ServeFile;
+use tracing::{debug, warn};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct FileLinkQuery {
+  expires: i64,
+  sig: String,
+}
+
+/// serve the local file of a link signed by FileAction PRESIGN
+pub(crate) async fn get_handler(
+  Path(link_path): Path<String>,
+  Query(query): Query<FileLinkQuery>,
+  req: Request,
+) -> Response {
+  let Some(file) = a2a_core::verify_file_link(&link_path, query.expires, &query.sig) else {
+    debug!(link_path, "invalid or expired file link");
+    return StatusCode::FORBIDDEN.into_response();
+  };
+
+  match ServeFile::new(&file).try_call(req).await {
+    Ok(res) => res.into_response(),
+    Err(err) => {
+      warn!(?file, %err, "serve file");
+      StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    }
+  }
+}
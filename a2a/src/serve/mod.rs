@@ -20,6 +20,7 @@ use tracing::{error, info};
 
 mod admin;
 mod api;
+mod files;
 mod mcp;
 mod run;
 mod scheduler;
@@ -51,6 +52,14 @@ pub(crate) async fn execute(arg: &Serve) -> Result<()> {
   .await??;
   let mcp_path = arg.mcp_path.as_ref().map_or("/mcp", |p| p.as_str());
 
+  if let Some(secret) = arg.files_secret.as_ref().filter(|s| !s.is_empty()) {
+    let base_url = arg
+      .public_url
+      .clone()
+      .unwrap_or_else(|| format!("http://{}", local_ip(arg)));
+    a2a_core::init_file_link(&base_url, secret);
+  }
+
   show_runtime_info(arg);
 
   let state = Arc::new(AppState {
@@ -77,7 +86,7 @@ pub(crate) async fn execute(arg: &Serve) -> Result<()> {
       HeaderName::from_static("authorization"),
     ])
     .allow_origin(Any);
-  let app = axum::Router::new()
+  let mut app = axum::Router::new()
     .fallback_service(tower_http::services::ServeDir::new(
       arg.html_root_path.clone(),
     ))
@@ -90,9 +99,11 @@ pub(crate) async fn execute(arg: &Serve) -> Result<()> {
     .route("/run/json", post(run::post_json_handle))
     .route("/run/form", post(run::post_form_handle))
     .route(admin_path, post(admin::post_handler))
-    .nest_service(mcp_path, mcp_http_service)
-    .with_state(state)
-    .layer(cors);
+    .nest_service(mcp_path, mcp_http_service);
+  if arg.files_secret.as_ref().is_some_and(|s| !s.is_empty()) {
+    app = app.route("/files/{*path}", get(files::get_handler));
+  }
+  let app = app.with_state(state).layer(cors);
 
   let listener = tokio::net::TcpListener::bind(&arg.listen).await?;
 
@@ -147,7 +158,7 @@ fn show_runtime_info(arg: &Serve) {
   let url = format!("http://{}", local_ip(arg));
   info!(url, "A2A listening on");
   let admin_path = arg.admin_path.as_ref().map_or("/admin", |p| p.as_str());
-  let routers = vec![
+  let mut routers = vec![
     ("/api/{*file}", "API endpoint"),
     ("/code", "Write code endpoint"),
     ("/code/prompt", "Get system prompt endpoint"),
@@ -159,6 +170,9 @@ fn show_runtime_info(arg: &Serve) {
     ),
     (admin_path, "Admin"),
   ];
+  if arg.files_secret.as_ref().is_some_and(|s| !s.is_empty()) {
+    routers.push(("/files/{*path}", "Signed local file links"));
+  }
 
   routers.iter().for_each(|(path, desc)| {
     let endpoint = format!("{}{}", url, path);
@@ -5,7 +5,7 @@ use std::{
   str::FromStr,
 };
 
-use a2a_types::Value;
+use a2a_types::{EMailAction, Value};
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use croner::Cron;
@@ -21,10 +21,14 @@ use crate::run::execute_js_file;
 pub struct ScheduledTask {
   pub name: String,
   #[serde(
+    default,
     serialize_with = "serialize_cron",
     deserialize_with = "deserialize_cron"
   )]
   pub crons: Vec<Cron>,
+  /// run the api script with the new emails as `params` instead of the crons, it is the EMailAction to 'RECV',
+  /// eg: `{"account": {...}, "folder": "INBOX", "filter": {"hasAttachment": true}}`
+  pub email: Option<Value>,
   pub command: String,
   pub args: Option<Vec<String>>,
   pub env: Option<HashMap<String, String>>,
@@ -36,6 +40,9 @@ pub struct ScheduledTask {
   pub params: Value,
   #[serde(skip)]
   pub is_a2a: bool,
+  /// the file to save the last seen email of the `email` trigger
+  #[serde(skip)]
+  pub email_state: Option<PathBuf>,
 }
 
 fn serialize_cron<S>(crons: &Vec<Cron>, s: S) -> Result<S::Ok, S::Error>
@@ -113,6 +120,28 @@ impl ScheduledTask {
     self.params = Value::Object(params);
   }
 
+  /// watch the new emails by IMAP IDLE, the api script runs with them as `params`
+  async fn watch_email(&self) -> Result<()> {
+    let mut email = self.email.clone().unwrap_or_default();
+    match email.as_object_mut() {
+      Some(o) => o.insert("method".to_string(), json!("RECV")),
+      None => return Err(anyhow::anyhow!("Invalid email trigger: {}", email)),
+    };
+    let action = serde_json::from_value::<EMailAction>(email)?;
+    if !self.is_a2a {
+      return Err(anyhow::anyhow!(
+        "the email trigger can only run the api script: {}",
+        self.command
+      ));
+    }
+    a2a_core::watch_email(action, self.email_state.clone(), |mails| async move {
+      let result = execute_js_file(&self.command, &self.conf, &mails, None).await?;
+      debug!(task = self.name, result= ?result, "task success");
+      Ok::<_, anyhow::Error>(())
+    })
+    .await
+  }
+
   async fn run_a2a(&self) -> Result<()> {
     let result = execute_js_file(&self.command, &self.conf, &self.params, None).await?;
     debug!(task = self.name, result= ?result, "task success");
@@ -190,7 +219,7 @@ pub(crate) async fn start(
     let (task_sender, task_recv) = mpsc::channel(8);
     states.insert(task.name.clone(), task_sender);
     debug!(task = task.name, "task started");
-    tokio::spawn(run_cron(task, task_recv));
+    spawn_task(task, task_recv);
   }
   states.sort_unstable_keys();
 
@@ -251,6 +280,18 @@ fn load_schedulers_file<P1: AsRef<Path>, P2: AsRef<Path>>(
       task.command = a2a_file.to_str().unwrap_or_default().to_string();
     }
     task.name = format!("{}/{}", relative_path(scheduler_root, file_name), task.name);
+    if task.email.is_some() {
+      // not `.json`, which is loaded as the scheduler
+      let state_dir = match scheduler_root.is_file() {
+        true => scheduler_root.parent().unwrap_or(scheduler_root),
+        false => scheduler_root,
+      };
+      task.email_state = Some(
+        state_dir
+          .join(".email")
+          .join(format!("{}.state", task.name)),
+      );
+    }
   });
   Ok(schedules)
 }
@@ -285,6 +326,50 @@ async fn load_schedulers(
   Ok(schedules)
 }
 
+fn spawn_task(task: ScheduledTask, event_recv: ScheduleAdminReceiver) {
+  if task.email.is_some() {
+    tokio::spawn(run_email(task, event_recv));
+  } else {
+    tokio::spawn(run_cron(task, event_recv));
+  }
+}
+
+/// the email trigger is stopped while paused, the emails arrived are read after resumed
+async fn run_email(task: ScheduledTask, mut event_recv: ScheduleAdminReceiver) {
+  let mut paused = false;
+  loop {
+    let (task_ref, is_paused) = (&task, paused);
+    let watch = async move {
+      if is_paused {
+        std::future::pending().await
+      } else {
+        task_ref.watch_email().await
+      }
+    };
+    tokio::select! {
+      r = watch => {
+        if let Err(err) = r {
+          warn!(task=task.name, err = ?err, "task email trigger failed");
+        }
+        break;
+      }
+      v = event_recv.recv() => {
+        info!(task=task.name, event=?v, "task event");
+        match v {
+          None => break,
+          Some(ScheduleEvent::Pause(_)) => paused = true,
+          Some(ScheduleEvent::Resume(_)) => paused = false,
+          Some(ScheduleEvent::Stop(_)) => {
+            break;
+          },
+          _ => {}
+        };
+      }
+    };
+  }
+  debug!(task = task.name, "task stopped");
+}
+
 async fn run_cron(task: ScheduledTask, mut event_recv: ScheduleAdminReceiver) {
   let (timer_sender, mut timer_recv) = mpsc::channel(2);
   timer_sender.send(()).await.ok();
@@ -422,7 +507,7 @@ async fn run_manager(
           let (task_sender, task_recv) = mpsc::channel(8);
           states.insert(task.name.clone(), task_sender);
           debug!(task = task.name, "task started");
-          tokio::spawn(run_cron(task, task_recv));
+          spawn_task(task, task_recv);
         }
         states.sort_unstable_keys();
       }
@@ -11,7 +11,9 @@ use serde::{Deserialize, Serialize};
 use tracing::{error, warn};
 
 use super::AppState;
-use crate::coder::{default_system_prompt, write_code_stream, DEFAULT_SYSTEM_PROMPT};
+use crate::coder::{
+  database_schema_prompt, default_system_prompt, write_code_stream, DEFAULT_SYSTEM_PROMPT,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WriteRequest {
@@ -22,7 +24,7 @@ pub struct WriteRequest {
 }
 
 pub async fn coder_handle(
-  State(_state): State<Arc<AppState>>,
+  State(state): State<Arc<AppState>>,
   Json(req): Json<WriteRequest>,
 ) -> Response<Body> {
   if writer_conf().base_url.is_empty() || writer_conf().api_key.is_empty() {
@@ -34,8 +36,14 @@ pub async fn coder_handle(
       .into_response();
   }
 
+  let mut system = writer_conf().system.clone();
+  let conf = state.conf.read().map(|c| c.clone()).unwrap_or_default();
+  if let Some(schema) = database_schema_prompt(&conf, &req.prompt).await {
+    system.push_str(&schema);
+  }
+
   let code = crate::coder::WriteCode {
-    system: writer_conf().system.clone(),
+    system,
     user: req.prompt,
     output: None,
     provider: req.provider,
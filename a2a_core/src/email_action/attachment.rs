@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+
+use a2a_types::EMailAction;
+use anyhow::Result;
+use opendal::Operator;
+use tracing::{debug, info, warn};
+
+use super::message::MailAttachment;
+use crate::file_action::path_operator;
+
+/// the max chars of the saved attachment name, the extension is kept
+const MAX_FILENAME_CHARS: usize = 120;
+
+/// the storage of the received attachments, each message has its own directory
+/// `<dir>/<user>/<folder>/<id>/`, the dir is a local path or any opendal url
+pub(super) struct AttachmentStore {
+  op: Operator,
+  /// the dir relative to the operator root
+  root: String,
+  /// the dir as configured, the prefix of the returned paths
+  base: String,
+  retention_days: Option<u64>,
+}
+
+impl AttachmentStore {
+  pub(super) fn from_action(action: &EMailAction) -> Result<Self> {
+    let base = match action.attachment_dir.as_deref().filter(|d| !d.is_empty()) {
+      Some(dir) => dir.to_string(),
+      None => std::env::temp_dir()
+        .join("a2a_email")
+        .to_string_lossy()
+        .to_string(),
+    };
+    let (op, root) = path_operator(&base, action.attachment_connection.as_ref())?;
+    Ok(Self {
+      op,
+      root: root.trim_end_matches(['/', '\\']).to_string(),
+      base: base.trim_end_matches(['/', '\\']).to_string(),
+      retention_days: action.attachment_retention_days.filter(|d| *d > 0),
+    })
+  }
+
+  /// save the attachments of the message, the result is the path of each one, none if failed
+  pub(super) async fn save(
+    &self,
+    user: &str,
+    folder: &str,
+    id: &str,
+    attachments: &[MailAttachment],
+  ) -> Vec<Option<String>> {
+    let dir = [user, folder, id]
+      .iter()
+      .map(|s| path_segment(s))
+      .collect::<Vec<_>>()
+      .join("/");
+    let mut names = HashSet::new();
+    let mut paths = Vec::new();
+    for (i, attachment) in attachments.iter().enumerate() {
+      let name = unique_filename(&sanitize_filename(&attachment.filename, i), &mut names);
+      let path = format!("{}/{}/{}", self.root, dir, name);
+      debug!(user, folder, id, path, "email save attachment");
+      match self.op.write(&path, attachment.body.clone()).await {
+        Ok(_) => paths.push(Some(format!("{}/{}/{}", self.base, dir, name))),
+        Err(err) => {
+          warn!(user, folder, id, path, %err, "email save attachment");
+          paths.push(None);
+        }
+      }
+    }
+    paths
+  }
+
+  /// remove the message directories of the user, which have no attachment saved in the retention days
+  pub(super) async fn cleanup(&self, user: &str) {
+    let Some(days) = self.retention_days else {
+      return;
+    };
+    let now = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis() as i64;
+    let expired_before = now - days as i64 * 24 * 3600 * 1000;
+
+    let user_dir = format!("{}/{}/", self.root, path_segment(user));
+    let entries = match self.op.list_with(&user_dir).recursive(true).await {
+      Ok(entries) => entries,
+      Err(err) => {
+        debug!(user, %err, "email list attachments");
+        return;
+      }
+    };
+    // the last modified of each message directory
+    let mut dirs = HashMap::<String, i64>::new();
+    for entry in entries.iter().filter(|e| e.metadata().is_file()) {
+      let Some((dir, _)) = entry.path().rsplit_once('/') else {
+        continue;
+      };
+      // some services (eg: fs) only return the entry mode in list
+      let modified = match entry.metadata().last_modified() {
+        Some(t) => Some(t),
+        None => self
+          .op
+          .stat(entry.path())
+          .await
+          .ok()
+          .and_then(|m| m.last_modified()),
+      }
+      .map(|t| t.into_inner().as_millisecond())
+      .unwrap_or(now);
+      let last = dirs.entry(format!("{}/", dir)).or_insert(modified);
+      *last = (*last).max(modified);
+    }
+
+    for (dir, _) in dirs.iter().filter(|(_, t)| **t < expired_before) {
+      info!(user, dir, "email remove expired attachments");
+      if let Err(err) = self.op.remove_all(dir).await {
+        warn!(user, dir, %err, "email remove expired attachments");
+      }
+    }
+  }
+}
+
+/// a single segment of the path, eg: the folder `[Gmail]/Sent Mail` is `_Gmail__Sent Mail`
+fn path_segment(s: &str) -> String {
+  let segment = s
+    .chars()
+    .map(|c| match c {
+      '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '[' | ']' => '_',
+      c if c.is_control() => '_',
+      c => c,
+    })
+    .collect::<String>();
+  match segment.trim_matches([' ', '.']) {
+    "" => "_".to_string(),
+    s => s.to_string(),
+  }
+}
+
+/// the filename without the directory, the chars invalid in the filename or the path are replaced,
+/// eg: `../../x` is `x`, `a:b?.txt` is `a_b_.txt`
+fn sanitize_filename(filename: &str, index: usize) -> String {
+  let name = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+  let name = path_segment(name);
+  let name = match name.as_str() {
+    "_" => format!("attachment{}", index + 1),
+    _ => name,
+  };
+  if name.chars().count() <= MAX_FILENAME_CHARS {
+    return name;
+  }
+  let (stem, ext) = split_ext(&name);
+  let ext = ext.chars().take(16).collect::<String>();
+  let stem = stem
+    .chars()
+    .take(MAX_FILENAME_CHARS - ext.chars().count())
+    .collect::<String>();
+  format!("{}{}", stem, ext)
+}
+
+/// the name not in the used names, eg: `invoice (2).pdf` for the second `invoice.pdf`
+fn unique_filename(name: &str, used: &mut HashSet<String>) -> String {
+  let mut unique = name.to_string();
+  let (stem, ext) = split_ext(name);
+  let mut n = 1;
+  // the names are case insensitive in some file systems
+  while !used.insert(unique.to_lowercase()) {
+    n += 1;
+    unique = format!("{} ({}){}", stem, n, ext);
+  }
+  unique
+}
+
+/// the stem and the extension with the dot
+fn split_ext(name: &str) -> (&str, &str) {
+  match name.rfind('.') {
+    Some(i) if i > 0 => name.split_at(i),
+    _ => (name, ""),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_sanitize_filename() {
+    assert_eq!(sanitize_filename("../../x", 0), "x");
+    assert_eq!(sanitize_filename("..\\..\\evil.exe", 0), "evil.exe");
+    assert_eq!(sanitize_filename("a:b?.txt", 0), "a_b_.txt");
+    assert_eq!(sanitize_filename("..", 1), "attachment2");
+    assert_eq!(sanitize_filename("dir/", 2), "attachment3");
+    let long = format!("{}.pdf", "a".repeat(300));
+    let name = sanitize_filename(&long, 0);
+    assert_eq!(name.chars().count(), MAX_FILENAME_CHARS);
+    assert!(name.ends_with(".pdf"));
+
+    assert_eq!(path_segment("[Gmail]/Sent Mail"), "_Gmail__Sent Mail");
+    assert_eq!(path_segment(".."), "_");
+
+    let mut used = HashSet::new();
+    assert_eq!(unique_filename("invoice.pdf", &mut used), "invoice.pdf");
+    assert_eq!(unique_filename("Invoice.pdf", &mut used), "Invoice (2).pdf");
+    assert_eq!(unique_filename("invoice.pdf", &mut used), "invoice (3).pdf");
+    assert_eq!(unique_filename("README", &mut used), "README");
+  }
+
+  #[tokio::test]
+  async fn test_save_attachments() {
+    let dir = std::env::temp_dir().join(format!("a2a_email_{}", crate::utils::uuid_v7()));
+    let action = EMailAction {
+      attachment_dir: Some(dir.to_string_lossy().to_string()),
+      ..Default::default()
+    };
+    let store = AttachmentStore::from_action(&action).unwrap();
+    let attachments = ["invoice.pdf", "invoice.pdf", "../../x"].map(|f| MailAttachment {
+      filename: f.to_string(),
+      body: f.as_bytes().to_vec(),
+    });
+    let paths = store.save("me@y.com", "INBOX", "12", &attachments).await;
+    let message_dir = dir.join("me@y.com").join("INBOX").join("12");
+    assert_eq!(
+      paths[1].as_deref().map(std::path::Path::new),
+      Some(message_dir.join("invoice (2).pdf").as_path())
+    );
+    assert_eq!(std::fs::read(message_dir.join("x")).unwrap(), b"../../x");
+    assert_eq!(std::fs::read_dir(&message_dir).unwrap().count(), 3);
+    std::fs::remove_dir_all(dir).ok();
+  }
+}
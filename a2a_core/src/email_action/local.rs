@@ -0,0 +1,318 @@
+use std::{cmp::Ordering, path::Path};
+
+use a2a_types::{EMailAction, Value};
+use anyhow::{anyhow, Result};
+use imap_client::imap_next::imap_types::extensions::sort::{SortCriterion, SortKey};
+use mail_parser::{
+  mailbox::{maildir, mbox},
+  MessageParser,
+};
+use serde_json::json;
+use tracing::{info, warn};
+
+use super::{
+  attachment::AttachmentStore,
+  message::{parse_mail, MailAttachment},
+  search::SearchOptions,
+};
+
+/// a message of the local source, the id is its number in the arrival order,
+/// so the ids are kept while the messages are only added, and shifted when any is removed
+struct LocalMail {
+  id: u64,
+  raw: Vec<u8>,
+  flags: Vec<&'static str>,
+}
+
+/// READ the messages of the `source`, an mbox file or a Maildir directory(with `cur` and `new`),
+/// the messages are matched and sorted like the IMAP messages without a server
+pub(super) async fn on_read_source(
+  source: &str,
+  filter: Option<&Value>,
+  options: &SearchOptions,
+  headers: &[String],
+  store: &AttachmentStore,
+) -> Result<Value> {
+  let path = Path::new(source.strip_prefix("file://").unwrap_or(source));
+  let mails = if path.is_dir() {
+    read_maildir(path)?
+  } else {
+    read_mbox(path)?
+  };
+  // the attachments are saved as the messages of the user named by the source
+  let user = path
+    .file_name()
+    .map(|n| n.to_string_lossy().to_string())
+    .unwrap_or_else(|| "local".to_string());
+  info!(source, count = mails.len(), ?options, "email read source");
+
+  let mut matched = Vec::new();
+  for mail in mails.into_iter().filter(|m| m.id > options.last_id) {
+    let Some((mut value, attachments)) = parse_mail(&mail.raw, headers) else {
+      warn!(source, id = mail.id, "email parse message failed");
+      continue;
+    };
+    value["id"] = json!(mail.id.to_string());
+    value["flags"] = json!(mail.flags);
+    if let Some(filter) = filter {
+      if !matches_filter(filter, &value)? {
+        continue;
+      }
+    }
+    matched.push((value, attachments, mail.raw.len()));
+  }
+  matched.sort_by(|a, b| compare_mails(&options.sort, (&a.0, a.2), (&b.0, b.2)));
+  matched.truncate(options.limit);
+
+  let mut result = Vec::new();
+  for (mut value, attachments, _) in matched {
+    save_attachments(store, &user, &mut value, &attachments).await;
+    result.push(value);
+  }
+  store.cleanup(&user).await;
+  Ok(Value::Array(result))
+}
+
+/// READ the email file `.eml` as a source of the single message with the id `1`,
+/// the attachments are saved in the `attachmentDir` of the options like READ
+pub(crate) async fn read_eml(raw: &[u8], path: &str, options: Option<&Value>) -> Result<Value> {
+  let action = EMailAction {
+    attachment_dir: options
+      .and_then(|o| o["attachmentDir"].as_str())
+      .map(String::from),
+    ..Default::default()
+  };
+  let store = AttachmentStore::from_action(&action)?;
+  let (mut value, attachments) =
+    parse_mail(raw, &[]).ok_or(anyhow!("Invalid email message: {}", path))?;
+  value["id"] = json!("1");
+  value["flags"] = json!(status_flags(raw));
+  let user = path.rsplit(['/', '\\']).next().unwrap_or("local");
+  save_attachments(&store, user, &mut value, &attachments).await;
+  Ok(value)
+}
+
+async fn save_attachments(
+  store: &AttachmentStore,
+  user: &str,
+  value: &mut Value,
+  attachments: &[MailAttachment],
+) {
+  let id = value["id"].as_str().unwrap_or_default().to_string();
+  let paths = store.save(user, "INBOX", &id, attachments).await;
+  for (i, path) in paths.into_iter().enumerate() {
+    value["attachments"][i]["path"] = json!(path);
+  }
+}
+
+/// the messages of the mbox in the file order, the flags are by the `Status` and `X-Status` headers
+fn read_mbox(path: &Path) -> Result<Vec<LocalMail>> {
+  let file = std::fs::File::open(path)
+    .map_err(|err| anyhow!("Open email source `{}` failed: {}", path.display(), err))?;
+  let mut mails = Vec::new();
+  for (i, message) in mbox::MessageIterator::new(std::io::BufReader::new(file)).enumerate() {
+    let raw = message?.unwrap_contents();
+    mails.push(LocalMail {
+      id: i as u64 + 1,
+      flags: status_flags(&raw),
+      raw,
+    });
+  }
+  Ok(mails)
+}
+
+/// the flags by the `Status` and `X-Status` headers of the mbox
+fn status_flags(raw: &[u8]) -> Vec<&'static str> {
+  let Some(message) = MessageParser::default().parse_headers(raw) else {
+    return Vec::new();
+  };
+  let status = [message.header_raw("Status"), message.header_raw("X-Status")]
+    .into_iter()
+    .flatten()
+    .collect::<String>();
+  [
+    ('R', "seen"),
+    ('A', "answered"),
+    ('F', "flagged"),
+    ('D', "deleted"),
+    ('T', "draft"),
+  ]
+  .into_iter()
+  .filter(|(c, _)| status.contains(*c))
+  .map(|(_, flag)| flag)
+  .collect()
+}
+
+/// the messages of the Maildir in the delivered order, the flags are by the info of the filenames
+fn read_maildir(path: &Path) -> Result<Vec<LocalMail>> {
+  let mut messages = maildir::MessageIterator::new(path)
+    .map_err(|err| anyhow!("Open email source `{}` failed: {}", path.display(), err))?
+    .collect::<std::io::Result<Vec<_>>>()?;
+  // the unique filenames start with the delivered time
+  messages.sort_by(|a, b| {
+    a.internal_date()
+      .cmp(&b.internal_date())
+      .then_with(|| a.path().file_name().cmp(&b.path().file_name()))
+  });
+  Ok(
+    messages
+      .into_iter()
+      .enumerate()
+      .map(|(i, message)| {
+        let flags = message
+          .flags()
+          .iter()
+          .map(|f| match f {
+            maildir::Flag::Passed => "passed",
+            maildir::Flag::Replied => "answered",
+            maildir::Flag::Seen => "seen",
+            maildir::Flag::Trashed => "deleted",
+            maildir::Flag::Draft => "draft",
+            maildir::Flag::Flagged => "flagged",
+          })
+          .collect();
+        LocalMail {
+          id: i as u64 + 1,
+          raw: message.unwrap_contents(),
+          flags,
+        }
+      })
+      .collect(),
+  )
+}
+
+/// whether the message matches all the filters, the text filters are case insensitive
+/// and the dates are compared with the `Date` header
+fn matches_filter(filter: &Value, mail: &Value) -> Result<bool> {
+  let filter = filter
+    .as_object()
+    .ok_or(anyhow!("Invalid email filter: {}", filter))?;
+  let has_flag = |flag: &str| {
+    mail["flags"]
+      .as_array()
+      .is_some_and(|f| f.iter().any(|f| f == flag))
+  };
+  for (name, value) in filter.iter().filter(|(_, v)| !v.is_null()) {
+    let matched = match name.as_str() {
+      "from" | "to" | "cc" | "subject" | "body" | "text" => {
+        let text = value
+          .as_str()
+          .ok_or(anyhow!("Invalid email filter `{}`: {}", name, value))?
+          .to_lowercase();
+        let fields = match name.as_str() {
+          "body" => vec![&mail["body"]],
+          "text" => vec![&mail["subject"], &mail["body"], &mail["from"], &mail["to"]],
+          name => vec![&mail[name]],
+        };
+        fields.iter().any(|f| {
+          match f {
+            Value::String(s) => s.clone(),
+            f => f.to_string(),
+          }
+          .to_lowercase()
+          .contains(&text)
+        })
+      }
+      "since" | "before" => {
+        let date = value
+          .as_str()
+          .and_then(|s| s.get(..10))
+          .ok_or(anyhow!("Invalid email filter date: {}", value))?;
+        match mail["date"].as_str().and_then(|d| d.get(..10)) {
+          Some(mail_date) if name == "since" => mail_date >= date,
+          Some(mail_date) => mail_date < date,
+          None => false,
+        }
+      }
+      "unseen" => bool_filter(name, value)? != has_flag("seen"),
+      "flagged" => bool_filter(name, value)? == has_flag("flagged"),
+      "hasAttachment" | "has_attachment" => {
+        let has_attachment = mail["attachments"]
+          .as_array()
+          .is_some_and(|a| a.iter().any(|a| a["inline"] != true));
+        bool_filter(name, value)? == has_attachment
+      }
+      name => anyhow::bail!("Unknown email filter `{}`", name),
+    };
+    if !matched {
+      return Ok(false);
+    }
+  }
+  Ok(true)
+}
+
+fn bool_filter(name: &str, value: &Value) -> Result<bool> {
+  value
+    .as_bool()
+    .ok_or(anyhow!("Invalid email filter `{}`: {}", name, value))
+}
+
+/// compare the messages by the sort criteria, the messages with size
+fn compare_mails(sort: &[SortCriterion], a: (&Value, usize), b: (&Value, usize)) -> Ordering {
+  let address = |v: &Value, field: &str| {
+    let address = match &v[field] {
+      Value::Array(a) => a.first().cloned().unwrap_or_default(),
+      v => v.clone(),
+    };
+    address["address"]
+      .as_str()
+      .unwrap_or_default()
+      .to_lowercase()
+  };
+  let id = |v: &Value| v["id"].as_str().and_then(|id| id.parse::<u64>().ok());
+  let date = |v: &Value| {
+    v["date"]
+      .as_str()
+      .and_then(|d| chrono::DateTime::parse_from_rfc3339(d).ok())
+  };
+  for criterion in sort {
+    let ordering = match criterion.key {
+      SortKey::Arrival => id(a.0).cmp(&id(b.0)),
+      SortKey::Date => date(a.0).cmp(&date(b.0)),
+      SortKey::From => address(a.0, "from").cmp(&address(b.0, "from")),
+      SortKey::To => address(a.0, "to").cmp(&address(b.0, "to")),
+      SortKey::Cc => address(a.0, "cc").cmp(&address(b.0, "cc")),
+      SortKey::Subject => a.0["subject"].as_str().cmp(&b.0["subject"].as_str()),
+      SortKey::Size => a.1.cmp(&b.1),
+      _ => Ordering::Equal,
+    };
+    let ordering = match criterion.reverse {
+      true => ordering.reverse(),
+      false => ordering,
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+  Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_matches_filter() {
+    let mail = json!({
+      "subject": "Invoice March",
+      "from": {"name": "Supplier", "address": "supplier@x.com"},
+      "to": [{"address": "me@y.com"}],
+      "date": "2024-03-04T10:00:00Z",
+      "body": "see the invoice",
+      "flags": ["seen"],
+      "attachments": [{"filename": "invoice.pdf", "inline": false}],
+    });
+    let matched = |filter: Value| matches_filter(&filter, &mail).unwrap();
+    assert!(matched(
+      json!({"from": "SUPPLIER@x.com", "subject": "invoice"})
+    ));
+    assert!(matched(
+      json!({"since": "2024-03-04", "before": "2024-03-05"})
+    ));
+    assert!(!matched(json!({"before": "2024-03-04T00:00:00Z"})));
+    assert!(matched(json!({"unseen": false, "flagged": false})));
+    assert!(matched(json!({"hasAttachment": true, "text": "march"})));
+    assert!(!matched(json!({"unseen": true})));
+    assert!(matches_filter(&json!({"unread": true}), &mail).is_err());
+  }
+}
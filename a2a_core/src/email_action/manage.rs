@@ -0,0 +1,205 @@
+use a2a_types::{EMailAction, Value};
+use anyhow::{anyhow, Result};
+use email::{
+  envelope::Id,
+  flag::{add::AddFlags, remove::RemoveFlags, Flag, Flags},
+  folder::{expunge::ExpungeFolder, list::ListFolders},
+  message::{copy::CopyMessages, r#move::MoveMessages},
+};
+use imap_client::imap_next::imap_types::search::SearchKey;
+use serde_json::json;
+use tracing::{info, warn};
+use utf7_imap::encode_utf7_imap;
+
+use super::imap_backend;
+
+/// change the messages of the ids returned by READ, the result is the ids.
+///
+/// - FLAG/UNFLAG: add or remove the `flags`, eg: `seen`, `flagged`, `answered` or a keyword
+/// - MOVE/COPY: to the `target` folder
+/// - DELETE: move to the `target` folder if any, eg: the trash, otherwise mark them deleted until EXPUNGE
+pub(super) async fn on_messages(action: &EMailAction, folder: &str) -> Result<Value> {
+  let ids = message_ids(action.ids.as_ref())?;
+  let id = Id::multiple(ids.clone());
+  let update = Update::from_action(action)?;
+
+  let backend = imap_backend(&action.account).await?;
+  info!(?update, folder, %id, "email update");
+  match update {
+    Update::AddFlags(flags) => backend.add_flags(folder, &id, &flags).await?,
+    Update::RemoveFlags(flags) => backend.remove_flags(folder, &id, &flags).await?,
+    Update::Move(target) => backend.move_messages(folder, &target, &id).await?,
+    Update::Copy(target) => backend.copy_messages(folder, &target, &id).await?,
+  }
+  Ok(json!(ids))
+}
+
+/// the change of the messages by the method, checked before connecting to the server
+#[derive(Debug, PartialEq)]
+enum Update {
+  AddFlags(Flags),
+  RemoveFlags(Flags),
+  Move(String),
+  Copy(String),
+}
+
+impl Update {
+  fn from_action(action: &EMailAction) -> Result<Self> {
+    let method = action.method.to_uppercase();
+    let flags = || {
+      let flags = action
+        .flags
+        .iter()
+        .flatten()
+        .map(|f| Flag::from(f.trim_start_matches('\\')))
+        .collect::<Flags>();
+      match flags.is_empty() {
+        true => Err(anyhow!("miss flags of the emails")),
+        false => Ok(flags),
+      }
+    };
+    let target = action.target.as_deref().filter(|t| !t.is_empty());
+    let update = match (method.as_str(), target) {
+      ("FLAG", _) => Self::AddFlags(flags()?),
+      ("UNFLAG", _) => Self::RemoveFlags(flags()?),
+      ("MOVE" | "DELETE", Some(target)) => Self::Move(target.to_string()),
+      ("COPY", Some(target)) => Self::Copy(target.to_string()),
+      ("DELETE", None) => Self::AddFlags(Flags::from_iter([Flag::Deleted])),
+      (method @ ("MOVE" | "COPY"), None) => {
+        return Err(anyhow!("miss target folder to {}", method))
+      }
+      (method, _) => return Err(anyhow!("Invalid method {}", method)),
+    };
+    Ok(update)
+  }
+}
+
+/// remove the messages marked deleted from the folder permanently
+pub(super) async fn on_expunge(account: &Value, folder: &str) -> Result<Value> {
+  let backend = imap_backend(account).await?;
+  info!(folder, "email expunge");
+  backend.expunge_folder(folder).await?;
+  Ok(Value::Null)
+}
+
+/// the folders with the counts of all and unseen messages,
+/// the counts are null if the folder can't be selected, eg: `[Gmail]`
+pub(super) async fn on_folders(account: &Value) -> Result<Value> {
+  let backend = imap_backend(account).await?;
+  let folders = backend.list_folders().await?;
+
+  let mut result = Vec::new();
+  let mut client = backend.context.client().await;
+  for folder in folders.iter() {
+    let mailbox = encode_utf7_imap(folder.name.clone());
+    let counts = match client.examine_mailbox(&mailbox).await {
+      Ok(data) => match client.search_uids([SearchKey::Unseen]).await {
+        Ok(unseen) => Some((data.exists.unwrap_or_default(), unseen.len())),
+        Err(err) => {
+          warn!(folder = folder.name, %err, "email count unseen");
+          None
+        }
+      },
+      Err(err) => {
+        warn!(folder = folder.name, %err, "email examine folder");
+        None
+      }
+    };
+    result.push(json!({
+      "name": folder.name,
+      "kind": folder.kind.as_ref().map(|k| k.as_str()),
+      "total": counts.map(|c| c.0),
+      "unseen": counts.map(|c| c.1),
+    }));
+  }
+  Ok(Value::Array(result))
+}
+
+/// the uids of a number, a string or an array of them
+fn message_ids(ids: Option<&Value>) -> Result<Vec<String>> {
+  let values = match ids {
+    Some(Value::Array(a)) => a.iter().collect(),
+    Some(Value::Null) | None => vec![],
+    Some(v) => vec![v],
+  };
+  let mut ids = Vec::new();
+  for v in values {
+    let id = match v {
+      Value::Number(n) => n.as_u64().map(|n| n.to_string()),
+      Value::String(s) => Some(s.trim().to_string()),
+      _ => None,
+    }
+    .filter(|id| id.parse::<u32>().is_ok_and(|n| n > 0))
+    .ok_or(anyhow!("Invalid email id: {}", v))?;
+    ids.push(id);
+  }
+  if ids.is_empty() {
+    return Err(anyhow!("miss ids of the emails"));
+  }
+  Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_message_ids() {
+    assert_eq!(
+      message_ids(Some(&json!([12, "13"]))).unwrap(),
+      vec!["12".to_string(), "13".to_string()]
+    );
+    assert_eq!(message_ids(Some(&json!(7))).unwrap(), vec!["7".to_string()]);
+    assert!(message_ids(None).is_err());
+    assert!(message_ids(Some(&json!(["1:*"]))).is_err());
+    assert!(message_ids(Some(&json!([0]))).is_err());
+  }
+
+  #[test]
+  fn test_update() {
+    let update = |method: &str, flags: Option<Vec<&str>>, target: Option<&str>| {
+      let action = EMailAction {
+        method: method.to_string(),
+        flags: flags.map(|f| f.into_iter().map(String::from).collect()),
+        target: target.map(String::from),
+        ..Default::default()
+      };
+      Update::from_action(&action).map_err(|err| err.to_string())
+    };
+    assert_eq!(
+      update("DELETE", None, None),
+      Ok(Update::AddFlags(Flags::from_iter([Flag::Deleted])))
+    );
+    assert_eq!(
+      update("delete", None, Some("Trash")),
+      Ok(Update::Move("Trash".to_string()))
+    );
+    assert_eq!(
+      update("FLAG", Some(vec!["\\Seen", "todo"]), None),
+      Ok(Update::AddFlags(Flags::from_iter([
+        Flag::Seen,
+        Flag::custom("todo")
+      ])))
+    );
+    assert_eq!(
+      update("UNFLAG", Some(vec!["flagged"]), None),
+      Ok(Update::RemoveFlags(Flags::from_iter([Flag::Flagged])))
+    );
+    assert_eq!(
+      update("COPY", None, Some("Archive")),
+      Ok(Update::Copy("Archive".to_string()))
+    );
+    assert_eq!(
+      update("MOVE", None, Some("")),
+      Err("miss target folder to MOVE".to_string())
+    );
+    assert_eq!(
+      update("FLAG", Some(vec![]), None),
+      Err("miss flags of the emails".to_string())
+    );
+    assert_eq!(
+      update("MARK", None, None),
+      Err("Invalid method MARK".to_string())
+    );
+  }
+}
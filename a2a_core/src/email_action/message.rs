@@ -0,0 +1,197 @@
+use a2a_types::Value;
+use mail_parser::{Address, HeaderValue, MessageParser, MessagePart, MimeHeaders};
+use serde_json::json;
+
+/// an attachment of the parsed message, the content is saved by the caller
+pub(crate) struct MailAttachment {
+  pub filename: String,
+  pub body: Vec<u8>,
+}
+
+/// the message json of the raw email, the attachments are in the order of `attachments` of the json,
+/// their `path` are null until saved.
+///
+/// the `body` is the plain text, converted from the HTML body if the message has no plain text body,
+/// the `headers` are the raw values of the headers requested, multiple values are joined by new line
+pub(crate) fn parse_mail(raw: &[u8], headers: &[String]) -> Option<(Value, Vec<MailAttachment>)> {
+  let message = MessageParser::default().parse(raw)?;
+
+  let message_id = message.message_id();
+  let in_reply_to = message_ids(message.in_reply_to());
+  let references = message_ids(message.references());
+  // the root of the thread, the message itself if it starts the thread
+  let thread_id = references
+    .first()
+    .or(in_reply_to.first())
+    .map(|id| id.as_str())
+    .or(message_id);
+
+  let mut raw_headers = serde_json::Map::new();
+  for name in headers {
+    let values = message
+      .headers_raw()
+      .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+      .map(|(_, v)| v.trim())
+      .collect::<Vec<_>>();
+    if !values.is_empty() {
+      raw_headers.insert(name.clone(), json!(values.join("\n")));
+    }
+  }
+
+  let mut attachments = Vec::new();
+  let mut files = Vec::new();
+  for (i, part) in message.attachments().enumerate() {
+    let mimetype = part_mimetype(part);
+    let filename = part
+      .attachment_name()
+      .map(|n| n.to_string())
+      .unwrap_or_else(|| default_filename(i, &mimetype));
+    let body = part.contents();
+    files.push(json!({
+      "filename": filename,
+      "mimetype": mimetype,
+      "size": body.len(),
+      "contentId": part.content_id(),
+      "inline": part.content_disposition().is_some_and(|d| d.is_inline()),
+      "path": null,
+    }));
+    attachments.push(MailAttachment {
+      filename,
+      body: body.to_vec(),
+    });
+  }
+
+  let text = message.body_text(0);
+  let value = json!({
+    "messageId": message_id,
+    "inReplyTo": in_reply_to.first(),
+    "references": references,
+    "threadId": thread_id,
+    "subject": message.subject(),
+    "date": message.date().map(|d| d.to_rfc3339()),
+    "from": message.from().and_then(|a| addresses(a).into_iter().next()),
+    "to": message.to().map(addresses).unwrap_or_default(),
+    "cc": message.cc().map(addresses).unwrap_or_default(),
+    "bcc": message.bcc().map(addresses).unwrap_or_default(),
+    "replyTo": message.reply_to().map(addresses).unwrap_or_default(),
+    "body": text.as_deref().unwrap_or_default(),
+    "html": message
+      .html_bodies()
+      .find(|p| p.is_text_html())
+      .and_then(|p| p.text_contents()),
+    "headers": raw_headers,
+    "attachments": files,
+  });
+  Some((value, attachments))
+}
+
+/// the addresses `{name, address}`, the members of the groups are flattened
+fn addresses(address: &Address) -> Vec<Value> {
+  address
+    .iter()
+    .filter(|a| a.address.is_some())
+    .map(|a| json!({"name": a.name, "address": a.address}))
+    .collect()
+}
+
+fn message_ids(value: &HeaderValue) -> Vec<String> {
+  match value {
+    HeaderValue::Text(id) => vec![id.to_string()],
+    HeaderValue::TextList(ids) => ids.iter().map(|id| id.to_string()).collect(),
+    _ => vec![],
+  }
+}
+
+fn part_mimetype(part: &MessagePart) -> String {
+  match part.content_type() {
+    Some(ct) => match ct.subtype() {
+      Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+      None => ct.ctype().to_string(),
+    },
+    None => "application/octet-stream".to_string(),
+  }
+  .to_lowercase()
+}
+
+/// the name of the attachment without filename, eg: `attachment1.png`
+fn default_filename(index: usize, mimetype: &str) -> String {
+  let ext = match mimetype {
+    "text/plain" => "txt",
+    "text/html" => "html",
+    "text/calendar" => "ics",
+    "image/jpeg" => "jpg",
+    "image/svg+xml" => "svg",
+    "message/rfc822" => "eml",
+    "application/pdf" => "pdf",
+    "application/zip" => "zip",
+    m if m.starts_with("image/") => &m[6..],
+    _ => return format!("attachment{}", index + 1),
+  };
+  format!("attachment{}.{}", index + 1, ext)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_mail() {
+    let raw = concat!(
+      "From: \"Supplier\" <supplier@x.com>\r\n",
+      "To: me@y.com, Ann <ann@y.com>\r\n",
+      "Cc: boss@y.com\r\n",
+      "Subject: Re: invoice\r\n",
+      "Date: Mon, 4 Mar 2024 10:00:00 +0000\r\n",
+      "Message-ID: <m2@x.com>\r\n",
+      "In-Reply-To: <m1@y.com>\r\n",
+      "References: <m0@y.com> <m1@y.com>\r\n",
+      "X-Priority: 1\r\n",
+      "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+      "\r\n",
+      "--b\r\n",
+      "Content-Type: text/html\r\n",
+      "\r\n",
+      "<p>see <b>invoice</b></p>\r\n",
+      "--b\r\n",
+      "Content-Type: application/pdf; name=\"invoice.pdf\"\r\n",
+      "Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n",
+      "Content-Transfer-Encoding: base64\r\n",
+      "\r\n",
+      "JVBERg==\r\n",
+      "--b\r\n",
+      "Content-Type: image/png\r\n",
+      "Content-Disposition: inline\r\n",
+      "Content-ID: <logo>\r\n",
+      "Content-Transfer-Encoding: base64\r\n",
+      "\r\n",
+      "iVBORw==\r\n",
+      "--b--\r\n",
+    );
+    let (value, attachments) = parse_mail(raw.as_bytes(), &["x-priority".to_string()]).unwrap();
+    assert_eq!(value["messageId"], "m2@x.com");
+    assert_eq!(value["inReplyTo"], "m1@y.com");
+    assert_eq!(value["threadId"], "m0@y.com");
+    assert_eq!(value["date"], "2024-03-04T10:00:00Z");
+    assert_eq!(
+      value["from"],
+      json!({"name": "Supplier", "address": "supplier@x.com"})
+    );
+    assert_eq!(
+      value["to"][1],
+      json!({"name": "Ann", "address": "ann@y.com"})
+    );
+    assert_eq!(value["cc"][0]["address"], "boss@y.com");
+    assert_eq!(value["headers"]["x-priority"], "1");
+    // the text is converted from the HTML body
+    assert_eq!(value["body"].as_str().unwrap().trim(), "see invoice");
+    assert!(value["html"].as_str().unwrap().contains("<b>invoice</b>"));
+
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0].filename, "invoice.pdf");
+    assert_eq!(attachments[0].body, b"%PDF");
+    assert_eq!(value["attachments"][0]["mimetype"], "application/pdf");
+    assert_eq!(value["attachments"][1]["filename"], "attachment2.png");
+    assert_eq!(value["attachments"][1]["contentId"], "logo");
+    assert_eq!(value["attachments"][1]["inline"], true);
+  }
+}
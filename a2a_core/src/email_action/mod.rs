@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use a2a_types::{EMailAction, EMailActionResult, Value};
+use anyhow::{anyhow, Result};
+use email::{
+  account::config::AccountConfig,
+  backend::{Backend, BackendBuilder},
+  imap::{ImapContext, ImapContextBuilder},
+};
+
+mod attachment;
+pub(crate) mod local;
+mod manage;
+mod message;
+mod read;
+mod search;
+mod send;
+mod watch;
+
+pub use watch::watch_email;
+
+pub async fn do_action(action: EMailAction) -> Result<EMailActionResult> {
+  let current_folder = action
+    .folder
+    .as_ref()
+    .map(|f| f.as_str())
+    .unwrap_or("INBOX");
+
+  match action.method.as_str().to_uppercase().as_str() {
+    "READ" | "RECV" => {
+      let options = search::SearchOptions::from_action(&action)?;
+      let headers = action.headers.as_deref().unwrap_or_default();
+      let store = attachment::AttachmentStore::from_action(&action)?;
+      match action.source.as_deref().filter(|s| !s.is_empty()) {
+        Some(source) => {
+          let filter = action.filter.as_ref();
+          local::on_read_source(source, filter, &options, headers, &store).await
+        }
+        None => read::on_read(&action.account, current_folder, &options, headers, &store).await,
+      }
+    }
+    method if action.source.is_some() => Err(anyhow!(
+      "the method {} is not supported by the email source",
+      method
+    )),
+    "SEND" => send::on_send(&action.account, action.message.as_ref()).await,
+    "FLAG" | "UNFLAG" | "MOVE" | "COPY" | "DELETE" => {
+      manage::on_messages(&action, current_folder).await
+    }
+    "EXPUNGE" => manage::on_expunge(&action.account, current_folder).await,
+    "FOLDERS" => manage::on_folders(&action.account).await,
+    _ => Err(anyhow::anyhow!("Invalid method")),
+  }
+}
+
+/// the imap backend of the account, the config is the `imap` of the account or the account itself
+async fn imap_backend(account: &Value) -> Result<Backend<ImapContext>> {
+  let account_config = Arc::new(AccountConfig::default());
+  let ctx_builder = account
+    .get("imap")
+    .or(Some(account))
+    .ok_or(anyhow!("miss imap config"))
+    .and_then(|v| serde_json::from_value(v.clone()).map_err(Into::into))
+    .map(Arc::new)
+    .map(|c| ImapContextBuilder::new(account_config.clone(), c))?;
+
+  if ctx_builder.imap_config.login.is_empty() {
+    return Err(anyhow!("miss user email"));
+  }
+
+  let builder = BackendBuilder::new(account_config, ctx_builder);
+  Ok(builder.build().await?)
+}
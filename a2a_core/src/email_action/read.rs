@@ -0,0 +1,76 @@
+use a2a_types::Value;
+use anyhow::Result;
+use email::{backend::Backend, envelope::Id, imap::ImapContext, message::peek::PeekMessages};
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use super::{
+  attachment::AttachmentStore,
+  imap_backend,
+  message::parse_mail,
+  search::{search_envelopes, SearchOptions},
+};
+
+pub(super) async fn on_read(
+  account: &Value,
+  folder: &str,
+  options: &SearchOptions,
+  headers: &[String],
+  store: &AttachmentStore,
+) -> Result<Value> {
+  let backend = imap_backend(account).await?;
+  let mails = read_mails(&backend, folder, options, headers, store).await?;
+  Ok(Value::Array(mails))
+}
+
+/// the parsed messages of the folder matched the options, the attachments are saved to the store
+pub(super) async fn read_mails(
+  backend: &Backend<ImapContext>,
+  folder: &str,
+  options: &SearchOptions,
+  headers: &[String],
+  store: &AttachmentStore,
+) -> Result<Vec<Value>> {
+  let user_email = backend.context.imap_config.login.clone();
+
+  info!(user_email, folder, ?options, "email check");
+  let envelopes = search_envelopes(&backend.context, folder, options).await?;
+
+  let mut mails = Vec::new();
+
+  info!(user_email, folder, count = envelopes.len(), "email fetch");
+  for envelope in envelopes.iter() {
+    let id = Id::Single(envelope.id.clone().into());
+    debug!(user_email, %id, "fetch message");
+    match backend.peek_messages(folder, &id).await {
+      Ok(msg) => {
+        if let Some(msg) = msg.first() {
+          match msg.raw().ok().and_then(|raw| parse_mail(raw, headers)) {
+            Some((mut mail, attachments)) => {
+              let paths = store
+                .save(&user_email, folder, &envelope.id, &attachments)
+                .await;
+              for (i, path) in paths.into_iter().enumerate() {
+                mail["attachments"][i]["path"] = json!(path);
+              }
+              mail["id"] = json!(envelope.id);
+              mail["flags"] = json!(Vec::<String>::from(envelope.flags.clone()));
+              mails.push(mail);
+            }
+            None => {
+              warn!(user_email, %id, "email parse message failed");
+            }
+          }
+        } else {
+          warn!(user_email, %id, "email empty message failed");
+        }
+      }
+      Err(err) => {
+        warn!(user_email, %id, %err, "email peek message failed");
+      }
+    }
+  }
+
+  store.cleanup(&user_email).await;
+  Ok(mails)
+}
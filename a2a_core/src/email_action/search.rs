@@ -0,0 +1,253 @@
+use std::num::NonZeroU32;
+
+use a2a_types::{EMailAction, Value};
+use anyhow::{anyhow, Result};
+use email::{envelope::Envelope, imap::ImapContext};
+use imap_client::imap_next::imap_types::{
+  core::{AString, Vec1},
+  datetime::NaiveDate,
+  extensions::sort::{SortCriterion, SortKey},
+  search::SearchKey,
+  sequence::{SeqOrUid, Sequence, SequenceSet},
+};
+use tracing::debug;
+use utf7_imap::encode_utf7_imap;
+
+/// the max messages of READ without `limit`
+const DEFAULT_LIMIT: usize = 30;
+
+/// the options of READ, the filters are the IMAP SEARCH keys except `hasAttachment`,
+/// which is checked by the body structure of the fetched envelopes
+#[derive(Debug, Clone)]
+pub(super) struct SearchOptions {
+  keys: Vec<SearchKey<'static>>,
+  has_attachment: Option<bool>,
+  pub(super) sort: Vec<SortCriterion>,
+  pub(super) limit: usize,
+  /// only the messages with greater uid
+  pub(super) last_id: u64,
+}
+
+impl SearchOptions {
+  pub(super) fn from_action(action: &EMailAction) -> Result<Self> {
+    let (keys, has_attachment) = match action.filter.as_ref() {
+      Some(filter) => search_keys(filter)?,
+      None => (vec![], None),
+    };
+    Ok(Self {
+      keys,
+      has_attachment,
+      sort: sort_criteria(action.sort.as_deref().unwrap_or("date desc"))?,
+      limit: action.limit.filter(|l| *l > 0).unwrap_or(DEFAULT_LIMIT),
+      last_id: action.last_id.unwrap_or_default(),
+    })
+  }
+}
+
+/// the envelopes of the folder matched the options, in the sort order.
+///
+/// the server without the SORT extension can only sort by the date or arrival,
+/// the uids of its SEARCH result are in the arrival order
+pub(super) async fn search_envelopes(
+  ctx: &ImapContext,
+  folder: &str,
+  options: &SearchOptions,
+) -> Result<Vec<Envelope>> {
+  let mut keys = options.keys.clone();
+  if let Some(uid) = u32::try_from(options.last_id + 1)
+    .ok()
+    .and_then(NonZeroU32::new)
+    .filter(|_| options.last_id > 0)
+  {
+    keys.push(SearchKey::uid(Sequence::Range(
+      SeqOrUid::Value(uid),
+      SeqOrUid::Asterisk,
+    )));
+  }
+  let criteria = Vec1::try_from(keys).unwrap_or_else(|_| Vec1::from(SearchKey::All));
+
+  let mut client = ctx.client().await;
+  let mailbox = ctx.account_config.get_folder_alias(folder);
+  client.select_mailbox(encode_utf7_imap(mailbox)).await?;
+
+  let mut uids = if client.ext_sort_supported() {
+    client.sort_uids(options.sort.clone(), criteria).await?
+  } else {
+    let mut uids = client.search_uids(criteria).await?;
+    uids.sort();
+    match options.sort.as_slice() {
+      [SortCriterion {
+        key: SortKey::Date | SortKey::Arrival,
+        reverse,
+      }] => {
+        if *reverse {
+          uids.reverse();
+        }
+      }
+      _ => anyhow::bail!("the IMAP server can only sort the messages by date or arrival"),
+    }
+    uids
+  };
+  // the range `n:*` matches the last message even if its uid is less than n
+  uids.retain(|uid| uid.get() as u64 > options.last_id);
+  debug!(folder, found = uids.len(), "email search");
+
+  let mut envelopes = Vec::new();
+  for chunk in uids.chunks(options.limit) {
+    let set = SequenceSet::try_from(chunk.to_vec()).map_err(|_| anyhow!("Invalid uids"))?;
+    let mut fetched = client.fetch_envelopes_map(set).await?;
+    envelopes.extend(
+      chunk
+        .iter()
+        .filter_map(|uid| fetched.remove(&uid.to_string()))
+        .filter(|e| options.has_attachment.is_none_or(|h| e.has_attachment == h)),
+    );
+    if envelopes.len() >= options.limit {
+      break;
+    }
+  }
+  envelopes.truncate(options.limit);
+  Ok(envelopes)
+}
+
+/// the search keys of the filter, all of them should match
+fn search_keys(filter: &Value) -> Result<(Vec<SearchKey<'static>>, Option<bool>)> {
+  let filter = filter
+    .as_object()
+    .ok_or(anyhow!("Invalid email filter: {}", filter))?;
+  let mut keys = Vec::new();
+  let mut has_attachment = None;
+  for (name, value) in filter.iter().filter(|(_, v)| !v.is_null()) {
+    let key = match name.as_str() {
+      "from" | "to" | "cc" | "subject" | "body" | "text" => {
+        let text = value
+          .as_str()
+          .ok_or(anyhow!("Invalid email filter `{}`: {}", name, value))?;
+        let text = AString::try_from(text.to_string())
+          .map_err(|err| anyhow!("Invalid email filter `{}`: {:?}", name, err))?;
+        match name.as_str() {
+          "from" => SearchKey::From(text),
+          "to" => SearchKey::To(text),
+          "cc" => SearchKey::Cc(text),
+          "subject" => SearchKey::Subject(text),
+          "body" => SearchKey::Body(text),
+          _ => SearchKey::Text(text),
+        }
+      }
+      // the received date of the messages
+      "since" => SearchKey::Since(search_date(value)?),
+      "before" => SearchKey::Before(search_date(value)?),
+      "unseen" => match flag_filter(name, value)? {
+        true => SearchKey::Unseen,
+        false => SearchKey::Seen,
+      },
+      "flagged" => match flag_filter(name, value)? {
+        true => SearchKey::Flagged,
+        false => SearchKey::Unflagged,
+      },
+      "hasAttachment" | "has_attachment" => {
+        has_attachment = Some(flag_filter(name, value)?);
+        continue;
+      }
+      name => anyhow::bail!("Unknown email filter `{}`", name),
+    };
+    keys.push(key);
+  }
+  Ok((keys, has_attachment))
+}
+
+fn flag_filter(name: &str, value: &Value) -> Result<bool> {
+  value
+    .as_bool()
+    .ok_or(anyhow!("Invalid email filter `{}`: {}", name, value))
+}
+
+/// the date of `2024-01-02` or the date part of the datetime, eg: `2024-01-02T03:04:05Z`
+fn search_date(value: &Value) -> Result<NaiveDate> {
+  let date = value
+    .as_str()
+    .and_then(|s| s.get(..10))
+    .and_then(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+    .ok_or(anyhow!("Invalid email filter date: {}", value))?;
+  NaiveDate::try_from(date).map_err(|err| anyhow!("Invalid email filter date {}: {:?}", value, err))
+}
+
+/// the sort keys separated by comma, each is the key and an optional order, eg: `from asc, date desc`
+fn sort_criteria(sort: &str) -> Result<Vec<SortCriterion>> {
+  let mut criteria = Vec::new();
+  for item in sort.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+    let mut parts = item.split_whitespace();
+    let key = match parts.next().unwrap_or_default().to_lowercase().as_str() {
+      "date" => SortKey::Date,
+      "arrival" => SortKey::Arrival,
+      "from" => SortKey::From,
+      "to" => SortKey::To,
+      "cc" => SortKey::Cc,
+      "subject" => SortKey::Subject,
+      "size" => SortKey::Size,
+      _ => anyhow::bail!("Invalid email sort: {}", item),
+    };
+    let reverse = match parts.next().map(|o| o.to_lowercase()).as_deref() {
+      None | Some("asc") => false,
+      Some("desc") => true,
+      _ => anyhow::bail!("Invalid email sort: {}", item),
+    };
+    criteria.push(SortCriterion { reverse, key });
+  }
+  if criteria.is_empty() {
+    anyhow::bail!("Invalid email sort: {}", sort);
+  }
+  Ok(criteria)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_search_options() {
+    let (keys, has_attachment) = search_keys(&json!({
+      "from": "supplier@x.com",
+      "since": "2024-03-04T00:00:00Z",
+      "unseen": true,
+      "flagged": false,
+      "hasAttachment": true,
+      "subject": null,
+    }))
+    .unwrap();
+    assert_eq!(has_attachment, Some(true));
+    assert_eq!(keys.len(), 4);
+    assert!(keys.contains(&SearchKey::Unseen));
+    assert!(keys.contains(&SearchKey::Unflagged));
+    assert!(keys.contains(&SearchKey::From(
+      AString::try_from("supplier@x.com".to_string()).unwrap()
+    )));
+    assert!(search_keys(&json!({"unread": true})).is_err());
+    assert!(search_keys(&json!({"since": "monday"})).is_err());
+
+    assert_eq!(
+      sort_criteria("from, date DESC").unwrap(),
+      vec![
+        SortCriterion {
+          reverse: false,
+          key: SortKey::From
+        },
+        SortCriterion {
+          reverse: true,
+          key: SortKey::Date
+        }
+      ]
+    );
+    assert!(sort_criteria("date down").is_err());
+
+    let action = EMailAction {
+      limit: Some(0),
+      ..Default::default()
+    };
+    let options = SearchOptions::from_action(&action).unwrap();
+    assert_eq!(options.limit, DEFAULT_LIMIT);
+    assert_eq!(options.sort.len(), 1);
+  }
+}
@@ -0,0 +1,301 @@
+use a2a_types::Value;
+use anyhow::{anyhow, Result};
+use email::smtp::{
+  build_client,
+  config::{SmtpAuthConfig, SmtpConfig},
+};
+use mail_builder::{headers::address::Address, MessageBuilder};
+use mail_send::{smtp::message as smtp, SmtpClientBuilder};
+use serde_json::json;
+use tracing::info;
+
+use crate::{
+  file_action::{mimetype_from_ext, path_operator},
+  utils::uuid_v7,
+};
+
+/// a mailbox of the message, the display name and the email address
+type Mailbox = (Option<String>, String);
+
+/// send the message by the smtp of the account.
+///
+/// the client is built by the email-lib smtp config, but the envelope is set here,
+/// so the `bcc` addresses are recipients without being written into the headers
+pub(super) async fn on_send(account: &Value, message: Option<&Value>) -> Result<Value> {
+  let smtp_config = smtp_config(account)?;
+  let message = message.ok_or(anyhow!("miss message to send"))?;
+
+  let from = match mailboxes(&message["from"])?.into_iter().next() {
+    Some(from) => from,
+    None if smtp_config.login.contains('@') => (None, smtp_config.login.clone()),
+    None => return Err(anyhow!("miss sender of the message")),
+  };
+  let to = mailboxes(&message["to"])?;
+  let cc = mailboxes(&message["cc"])?;
+  let bcc = mailboxes(&message["bcc"])?;
+  let recipients = to
+    .iter()
+    .chain(cc.iter())
+    .chain(bcc.iter())
+    .map(|(_, email)| email.clone())
+    .collect::<Vec<_>>();
+  if recipients.is_empty() {
+    return Err(anyhow!("miss recipients of the message"));
+  }
+
+  let domain = from.1.rsplit('@').next().unwrap_or("localhost");
+  let message_id = format!("{}@{}", uuid_v7(), domain);
+  let mut builder = MessageBuilder::new()
+    .message_id(message_id.as_str())
+    .from(address(&from));
+  if !to.is_empty() {
+    builder = builder.to(address_list(&to));
+  }
+  if !cc.is_empty() {
+    builder = builder.cc(address_list(&cc));
+  }
+  let reply_to = mailboxes(&message["replyTo"])?;
+  if !reply_to.is_empty() {
+    builder = builder.reply_to(address_list(&reply_to));
+  }
+  let in_reply_to = message_ids(&message["inReplyTo"]);
+  if !in_reply_to.is_empty() {
+    builder = builder.in_reply_to(in_reply_to);
+  }
+  // the reply is in the thread of the message it replies to
+  let references = match message_ids(&message["references"]) {
+    references if references.is_empty() => message_ids(&message["inReplyTo"]),
+    references => references,
+  };
+  if !references.is_empty() {
+    builder = builder.references(references);
+  }
+  if let Some(subject) = message["subject"].as_str() {
+    builder = builder.subject(subject);
+  }
+  if let Some(text) = message["text"].as_str() {
+    builder = builder.text_body(text);
+  }
+  if let Some(html) = message["html"].as_str() {
+    builder = builder.html_body(html);
+  }
+  for (i, attachment) in message["attachments"]
+    .as_array()
+    .into_iter()
+    .flatten()
+    .enumerate()
+  {
+    let (filename, mimetype, body) = read_attachment(attachment, i).await?;
+    builder = builder.attachment(mimetype, filename, body);
+  }
+  let body = builder.write_to_vec()?;
+
+  let mut client_builder = SmtpClientBuilder::new(smtp_config.host.clone(), smtp_config.port)
+    .implicit_tls(!smtp_config.is_start_tls_encryption_enabled());
+  if !matches!(&smtp_config.auth, SmtpAuthConfig::Password(p) if p.is_empty()) {
+    client_builder = client_builder.credentials(smtp_config.credentials().await?);
+  }
+  let (_, mut client) = build_client(&smtp_config, client_builder).await?;
+
+  info!(from = from.1, recipients = recipients.len(), "email send");
+  client
+    .send(smtp::Message {
+      mail_from: envelope_address(&from.1),
+      rcpt_to: recipients.iter().map(|r| envelope_address(r)).collect(),
+      body: body.into(),
+    })
+    .await?;
+
+  Ok(json!({
+    "messageId": message_id,
+    "recipients": recipients,
+  }))
+}
+
+/// the smtp config of the account, the relay without authentication has no `auth`
+fn smtp_config(account: &Value) -> Result<SmtpConfig> {
+  let mut smtp = account.get("smtp").unwrap_or(account).clone();
+  let auth = match smtp.as_object_mut() {
+    Some(o) => o.remove("auth"),
+    None => return Err(anyhow!("miss smtp config")),
+  };
+  smtp["auth"] = json!({"type": "password", "raw": ""});
+  let mut config: SmtpConfig = serde_json::from_value(smtp)?;
+  config.auth = auth
+    .map(serde_json::from_value)
+    .transpose()?
+    .unwrap_or_default();
+  Ok(config)
+}
+
+/// the mailboxes of the field, a string of comma separated addresses, eg: `Tom <tom@a.com>, b@a.com`,
+/// an array of them, or the objects `{name, address}`
+fn mailboxes(v: &Value) -> Result<Vec<Mailbox>> {
+  let mut result = Vec::new();
+  match v {
+    Value::Null => {}
+    Value::String(s) => {
+      for item in split_addresses(s) {
+        result.push(parse_mailbox(item)?);
+      }
+    }
+    Value::Array(a) => {
+      for item in a {
+        result.extend(mailboxes(item)?);
+      }
+    }
+    Value::Object(o) => {
+      let email = o
+        .get("address")
+        .and_then(|a| a.as_str())
+        .ok_or(anyhow!("miss address of {}", v))?;
+      let (_, email) = parse_mailbox(email)?;
+      let name = o
+        .get("name")
+        .and_then(|n| n.as_str())
+        .map(|n| n.to_string());
+      result.push((name.filter(|n| !n.is_empty()), email));
+    }
+    v => return Err(anyhow!("Invalid email address: {}", v)),
+  }
+  Ok(result)
+}
+
+/// split the addresses by comma, except the comma in the quoted name, eg: `"Doe, John" <j@a.com>`
+fn split_addresses(s: &str) -> impl Iterator<Item = &str> {
+  let mut quoted = false;
+  s.split(move |c| {
+    if c == '"' {
+      quoted = !quoted;
+    }
+    c == ',' && !quoted
+  })
+  .map(|s| s.trim())
+  .filter(|s| !s.is_empty())
+}
+
+/// `Tom <tom@a.com>`, `"Tom" <tom@a.com>` or `tom@a.com`
+fn parse_mailbox(s: &str) -> Result<Mailbox> {
+  let s = s.trim();
+  let (name, email) = match s.strip_suffix('>').and_then(|s| s.rsplit_once('<')) {
+    Some((name, email)) => {
+      let name = name.trim().trim_matches('"').trim();
+      ((!name.is_empty()).then(|| name.to_string()), email.trim())
+    }
+    None => (None, s),
+  };
+  if email.is_empty() || !email.contains('@') || email.contains(char::is_whitespace) {
+    return Err(anyhow!("Invalid email address: {}", s));
+  }
+  Ok((name, email.to_string()))
+}
+
+fn address(mailbox: &Mailbox) -> Address<'_> {
+  Address::new_address(mailbox.0.as_deref(), mailbox.1.as_str())
+}
+
+fn address_list(mailboxes: &[Mailbox]) -> Address<'_> {
+  Address::new_list(mailboxes.iter().map(address).collect())
+}
+
+fn envelope_address(email: &str) -> smtp::Address<'_> {
+  smtp::Address {
+    email: email.into(),
+    ..Default::default()
+  }
+}
+
+/// the message ids of a string or an array, without the angle brackets
+fn message_ids(v: &Value) -> Vec<String> {
+  let ids = match v {
+    Value::String(s) => s.split_whitespace().collect::<Vec<_>>(),
+    Value::Array(a) => a.iter().filter_map(|id| id.as_str()).collect(),
+    _ => vec![],
+  };
+  ids
+    .into_iter()
+    .map(|id| {
+      id.trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+    })
+    .filter(|id| !id.is_empty())
+    .collect()
+}
+
+/// the filename, mimetype and content of the attachment, it is a local path, a data url,
+/// or the object `{path, filename, contentType}`
+async fn read_attachment(attachment: &Value, index: usize) -> Result<(String, String, Vec<u8>)> {
+  let (path, filename, mimetype) = match attachment {
+    Value::String(path) => (path.as_str(), None, None),
+    Value::Object(o) => (
+      o.get("path")
+        .and_then(|p| p.as_str())
+        .ok_or(anyhow!("miss path of the attachment {}", index))?,
+      o.get("filename").and_then(|f| f.as_str()),
+      o.get("contentType").and_then(|m| m.as_str()),
+    ),
+    _ => return Err(anyhow!("Invalid attachment: {}", attachment)),
+  };
+
+  if let Some(data_url) = path.strip_prefix("data:") {
+    let (meta, data) = data_url
+      .split_once(',')
+      .ok_or(anyhow!("Invalid data url of the attachment {}", index))?;
+    let body = match meta.strip_suffix(";base64") {
+      Some(_) => base64_simd::STANDARD
+        .decode_to_vec(data.as_bytes())
+        .map_err(|err| anyhow!("Invalid data url of the attachment {}: {}", index, err))?,
+      None => urlencoding::decode_binary(data.as_bytes()).into_owned(),
+    };
+    let meta_type = meta.split(';').next().filter(|m| !m.is_empty());
+    let mimetype = mimetype.or(meta_type).unwrap_or("application/octet-stream");
+    let filename = filename
+      .map(|f| f.to_string())
+      .unwrap_or_else(|| format!("attachment{}", index + 1));
+    return Ok((filename, mimetype.to_string(), body));
+  }
+
+  let (op, rel) = path_operator(path, None)?;
+  let body = op
+    .read(&rel)
+    .await
+    .map_err(|err| anyhow!("Read attachment `{}` failed: {}", path, err))?
+    .to_vec();
+  let filename = filename
+    .unwrap_or_else(|| path.rsplit(['/', '\\']).next().unwrap_or(path))
+    .to_string();
+  let mimetype = mimetype
+    .map(|m| m.to_string())
+    .unwrap_or_else(|| mimetype_from_ext(&filename));
+  Ok((filename, mimetype, body))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_mailboxes() {
+    assert_eq!(
+      mailboxes(&json!(r#""Doe, John" <j@a.com>, b@a.com"#)).unwrap(),
+      vec![
+        (Some("Doe, John".to_string()), "j@a.com".to_string()),
+        (None, "b@a.com".to_string())
+      ]
+    );
+    assert_eq!(
+      mailboxes(&json!(["Tom <tom@a.com>", {"name": "Ann", "address": "ann@a.com"}])).unwrap(),
+      vec![
+        (Some("Tom".to_string()), "tom@a.com".to_string()),
+        (Some("Ann".to_string()), "ann@a.com".to_string())
+      ]
+    );
+    assert!(mailboxes(&json!("not an address")).is_err());
+    assert_eq!(
+      message_ids(&json!("<a@x> <b@x>")),
+      vec!["a@x".to_string(), "b@x".to_string()]
+    );
+  }
+}
@@ -0,0 +1,429 @@
+use std::{
+  future::Future,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use a2a_types::{EMailAction, Value};
+use anyhow::Result;
+use email::{backend::Backend, imap::ImapContext};
+use imap_client::imap_next::imap_types::{
+  extensions::sort::{SortCriterion, SortKey},
+  response::Capability,
+  search::SearchKey,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use utf7_imap::encode_utf7_imap;
+
+use super::{attachment::AttachmentStore, imap_backend, read::read_mails, search::SearchOptions};
+
+/// the delay before reconnecting, doubled after each failure up to the max
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(300);
+/// the interval to check the new messages if the server doesn't support IDLE
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// the attempts to handle the new messages, they are skipped after all failed
+const HANDLE_ATTEMPTS: u32 = 3;
+
+/// the last seen uid of the folder, the uids are only valid in the same uid validity
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WatchState {
+  uid_validity: Option<u32>,
+  last_id: Option<u64>,
+}
+
+impl WatchState {
+  fn load(path: Option<&Path>) -> Option<Self> {
+    let content = std::fs::read_to_string(path?).ok()?;
+    serde_json::from_str(&content)
+      .inspect_err(|err| warn!(?path, %err, "email watch state invalid"))
+      .ok()
+  }
+
+  fn save(&self, path: Option<&Path>) {
+    let Some(path) = path else {
+      return;
+    };
+    if let Some(dir) = path.parent() {
+      std::fs::create_dir_all(dir).ok();
+    }
+    let content = serde_json::to_string(self).unwrap_or_default();
+    if let Err(err) = std::fs::write(path, content) {
+      warn!(?path, %err, "email watch save state");
+    }
+  }
+
+  /// the state of the selected folder, it starts from the newest message
+  /// if it has no last id or the uid validity of the folder is changed
+  fn resume(&self, uid_validity: u32, newest: impl FnOnce() -> u64) -> Self {
+    match (self.uid_validity, self.last_id) {
+      (Some(v), Some(_)) if v == uid_validity => self.clone(),
+      // the last id is given without the state
+      (None, Some(last_id)) => Self {
+        uid_validity: Some(uid_validity),
+        last_id: Some(last_id),
+      },
+      (validity, _) => {
+        if validity.is_some() {
+          warn!(
+            old = validity,
+            new = uid_validity,
+            "email watch uid validity changed"
+          );
+        }
+        Self {
+          uid_validity: Some(uid_validity),
+          last_id: Some(newest()),
+        }
+      }
+    }
+  }
+}
+
+/// watch the new messages of the folder by IMAP IDLE, `on_mails` is called with the parsed messages
+/// in the arrival order, like READ with the `filter`, `headers` and attachment options of the action.
+///
+/// the last seen uid is saved to the `state_file`, so the messages arrived while stopped are read
+/// when started again. without the state or `lastId`, only the messages arrived after started are read.
+/// the messages failed to handle are retried before skipped.
+/// it reconnects after the connection is lost or the folder failed to read, and only returns the error of the invalid action
+pub async fn watch_email<F, Fut>(
+  action: EMailAction,
+  state_file: Option<PathBuf>,
+  mut on_mails: F,
+) -> Result<()>
+where
+  F: FnMut(Value) -> Fut,
+  Fut: Future<Output = Result<()>>,
+{
+  let folder = action.folder.as_deref().unwrap_or("INBOX");
+  let mut options = SearchOptions::from_action(&action)?;
+  options.sort = vec![SortCriterion {
+    reverse: false,
+    key: SortKey::Arrival,
+  }];
+  let headers = action.headers.as_deref().unwrap_or_default();
+  let store = AttachmentStore::from_action(&action)?;
+  let state_file = state_file.as_deref();
+  let mut state = WatchState::load(state_file).unwrap_or(WatchState {
+    uid_validity: None,
+    last_id: action.last_id.filter(|id| *id > 0),
+  });
+
+  // the delay is reset after the folder is read, so a missing folder is retried less and less often
+  let mut delay = RECONNECT_DELAY;
+  loop {
+    let watched = match imap_backend(&action.account).await {
+      Ok(backend) => {
+        watch_folder(
+          &backend,
+          folder,
+          (&options, headers, &store),
+          (&mut state, state_file),
+          &mut on_mails,
+          &mut delay,
+        )
+        .await
+      }
+      Err(err) => Err(err),
+    };
+    if let Err(err) = watched {
+      warn!(folder, %err, ?delay, "email watch disconnected");
+      tokio::time::sleep(delay).await;
+      delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+  }
+}
+
+/// read the new messages and wait for the changes of the folder until the connection failed
+async fn watch_folder<F, Fut>(
+  backend: &Backend<ImapContext>,
+  folder: &str,
+  (options, headers, store): (&SearchOptions, &[String], &AttachmentStore),
+  (state, state_file): (&mut WatchState, Option<&Path>),
+  on_mails: &mut F,
+  delay: &mut Duration,
+) -> Result<()>
+where
+  F: FnMut(Value) -> Fut,
+  Fut: Future<Output = Result<()>>,
+{
+  let mailbox = encode_utf7_imap(backend.context.account_config.get_folder_alias(folder));
+  // the capabilities are not kept by the client of the backend, they are got by a new session
+  let mut builder = backend.context.client().await.client_builder.clone();
+  let wait = Wait::from_capabilities(builder.build().await?.state.capabilities_iter());
+  debug!(folder, ?wait, "email watch");
+  loop {
+    let (resumed, uid_next) = {
+      let mut client = backend.context.client().await;
+      let data = client.select_mailbox(&mailbox).await?;
+      let uid_next = data.uid_next.map(|n| n.get());
+      let newest = match uid_next {
+        Some(next) => next as u64 - 1,
+        None => client
+          .search_uids([SearchKey::All])
+          .await?
+          .iter()
+          .map(|uid| uid.get() as u64)
+          .max()
+          .unwrap_or_default(),
+      };
+      let resumed = state.resume(
+        data.uid_validity.map(|v| v.get()).unwrap_or_default(),
+        || newest,
+      );
+      (resumed, uid_next)
+    };
+    if resumed != *state {
+      *state = resumed;
+      state.save(state_file);
+    }
+
+    let read = |last_id| {
+      let mut options = options.clone();
+      options.last_id = last_id;
+      async move { read_mails(backend, folder, &options, headers, store).await }
+    };
+    read_new(
+      read,
+      folder,
+      options.limit,
+      (state, state_file),
+      on_mails,
+      RECONNECT_DELAY,
+    )
+    .await?;
+    *delay = RECONNECT_DELAY;
+
+    let mut client = backend.context.client().await;
+    let data = client.select_mailbox(&mailbox).await?;
+    // the messages arrived while reading are not notified by IDLE
+    if data.uid_next.map(|n| n.get()) != uid_next {
+      continue;
+    }
+    match wait {
+      Wait::Idle => {
+        // keep the sender to idle until the folder changed or timed out
+        let (_shutdown, mut shutdown_request) = tokio::sync::oneshot::channel();
+        debug!(folder, "email watch idle");
+        client.idle(&mut shutdown_request).await?;
+      }
+      Wait::Poll(interval) => {
+        drop(client);
+        debug!(folder, ?interval, "email watch poll");
+        tokio::time::sleep(interval).await;
+      }
+    }
+  }
+}
+
+/// how to wait for the changes of the folder
+#[derive(Debug, PartialEq)]
+enum Wait {
+  Idle,
+  Poll(Duration),
+}
+
+impl Wait {
+  /// IDLE if the server has the capability, otherwise check the new messages by the interval
+  fn from_capabilities<'a>(
+    mut capabilities: impl Iterator<Item = &'a Capability<'static>>,
+  ) -> Self {
+    match capabilities.any(|c| *c == Capability::Idle) {
+      true => Self::Idle,
+      false => Self::Poll(POLL_INTERVAL),
+    }
+  }
+}
+
+/// read the new messages after the last id in batches of the limit, the last id is saved after
+/// the messages are handled, or skipped after all the attempts failed
+async fn read_new<R, RFut, F, Fut>(
+  mut read: R,
+  folder: &str,
+  limit: usize,
+  (state, state_file): (&mut WatchState, Option<&Path>),
+  on_mails: &mut F,
+  retry_delay: Duration,
+) -> Result<()>
+where
+  R: FnMut(u64) -> RFut,
+  RFut: Future<Output = Result<Vec<Value>>>,
+  F: FnMut(Value) -> Fut,
+  Fut: Future<Output = Result<()>>,
+{
+  loop {
+    let mails = read(state.last_id.unwrap_or_default()).await?;
+    let Some(last_id) = mails
+      .iter()
+      .filter_map(|m| m["id"].as_str().and_then(|id| id.parse::<u64>().ok()))
+      .max()
+    else {
+      return Ok(());
+    };
+    let count = mails.len();
+    info!(folder, count, last_id, "email watch new messages");
+    let mails = Value::Array(mails);
+    let mut attempt = 1;
+    while let Err(err) = on_mails(mails.clone()).await {
+      if attempt >= HANDLE_ATTEMPTS {
+        warn!(folder, last_id, %err, attempt, "email watch skip messages");
+        break;
+      }
+      warn!(folder, last_id, %err, attempt, "email watch handle messages failed");
+      attempt += 1;
+      tokio::time::sleep(retry_delay).await;
+    }
+    state.last_id = Some(last_id);
+    state.save(state_file);
+    if count < limit {
+      return Ok(());
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_watch_state() {
+    let state = |uid_validity, last_id| WatchState {
+      uid_validity,
+      last_id,
+    };
+    // start from the newest message
+    assert_eq!(state(None, None).resume(7, || 42), state(Some(7), Some(42)));
+    // the given last id
+    assert_eq!(
+      state(None, Some(3)).resume(7, || 42),
+      state(Some(7), Some(3))
+    );
+    assert_eq!(
+      state(Some(7), Some(3)).resume(7, || 42),
+      state(Some(7), Some(3))
+    );
+    // the uids of the old validity are meaningless
+    assert_eq!(
+      state(Some(6), Some(3)).resume(7, || 42),
+      state(Some(7), Some(42))
+    );
+
+    let path = std::env::temp_dir()
+      .join("a2a_test")
+      .join(crate::utils::uuid_v7())
+      .join("watch.state");
+    assert_eq!(WatchState::load(Some(&path)), None);
+    state(Some(7), Some(3)).save(Some(&path));
+    assert_eq!(WatchState::load(Some(&path)), Some(state(Some(7), Some(3))));
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+  }
+
+  #[test]
+  fn test_wait() {
+    let wait =
+      |capabilities: Vec<Capability<'static>>| Wait::from_capabilities(capabilities.iter());
+    assert_eq!(
+      wait(vec![Capability::Imap4Rev1, Capability::Idle]),
+      Wait::Idle
+    );
+    assert_eq!(wait(vec![Capability::Imap4Rev1]), Wait::Poll(POLL_INTERVAL));
+  }
+
+  #[tokio::test]
+  async fn test_read_new() {
+    // the stand-in folder of the messages with the uids 3 to 7
+    let read = |last_id: u64| async move {
+      Ok(
+        (3..=7u64)
+          .filter(|id| *id > last_id)
+          .take(2)
+          .map(|id| json!({"id": id.to_string()}))
+          .collect::<Vec<_>>(),
+      )
+    };
+    let mut state = WatchState {
+      uid_validity: Some(7),
+      last_id: Some(3),
+    };
+    let path = std::env::temp_dir()
+      .join("a2a_test")
+      .join(crate::utils::uuid_v7())
+      .join("watch.state");
+
+    // the batches are handled in order, the failed one is retried
+    let mut handled = Vec::new();
+    let mut failures = 1;
+    let mut on_mails = |mails: Value| {
+      let result = match failures {
+        0 => {
+          handled.push(mails);
+          Ok(())
+        }
+        _ => {
+          failures -= 1;
+          Err(anyhow::anyhow!("handle failed"))
+        }
+      };
+      async move { result }
+    };
+    let result = read_new(
+      read,
+      "INBOX",
+      2,
+      (&mut state, Some(&path)),
+      &mut on_mails,
+      Duration::ZERO,
+    )
+    .await;
+    assert!(result.is_ok());
+    assert_eq!(
+      handled,
+      vec![
+        json!([{"id": "4"}, {"id": "5"}]),
+        json!([{"id": "6"}, {"id": "7"}])
+      ]
+    );
+    assert_eq!(state.last_id, Some(7));
+    assert_eq!(WatchState::load(Some(&path)), Some(state.clone()));
+
+    // the messages failed in all the attempts are skipped
+    state.last_id = Some(5);
+    let mut attempts = 0;
+    let mut on_mails = |_| {
+      attempts += 1;
+      async { Err(anyhow::anyhow!("handle failed")) }
+    };
+    read_new(
+      read,
+      "INBOX",
+      5,
+      (&mut state, Some(&path)),
+      &mut on_mails,
+      Duration::ZERO,
+    )
+    .await
+    .unwrap();
+    assert_eq!(attempts, HANDLE_ATTEMPTS);
+    assert_eq!(state.last_id, Some(7));
+
+    // the failed read keeps the last id
+    state.last_id = Some(5);
+    let result = read_new(
+      |_| async { Err::<Vec<Value>, _>(anyhow::anyhow!("folder not found")) },
+      "INBOX",
+      5,
+      (&mut state, Some(&path)),
+      &mut |_| async { Ok(()) },
+      Duration::ZERO,
+    )
+    .await;
+    assert!(result.is_err());
+    assert_eq!(state.last_id, Some(5));
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+  }
+}
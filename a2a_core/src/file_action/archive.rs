@@ -0,0 +1,486 @@
+use std::io::{Cursor, Read, Write};
+
+use a2a_tojson::{bytes_to_json, to_mimetype_bytes};
+use a2a_types::{FileAction, FileActionResult, Value};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use glob::{MatchOptions, Pattern};
+use opendal::Operator;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use super::{
+  list::{expand_braces, glob_files, split_glob},
+  mimetype_from_ext, path_operator,
+};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+  case_sensitive: true,
+  require_literal_separator: true,
+  require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArchiveFormat {
+  Zip,
+  Tar,
+  TarGz,
+  TarZst,
+  SevenZ,
+}
+
+impl ArchiveFormat {
+  fn from_path(path: &str) -> Option<Self> {
+    let path = path.to_lowercase();
+    if path.ends_with(".zip") {
+      Some(Self::Zip)
+    } else if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+      Some(Self::TarGz)
+    } else if path.ends_with(".tar.zst") || path.ends_with(".tzst") {
+      Some(Self::TarZst)
+    } else if path.ends_with(".tar") {
+      Some(Self::Tar)
+    } else if path.ends_with(".7z") {
+      Some(Self::SevenZ)
+    } else {
+      None
+    }
+  }
+
+  fn from_scheme(scheme: &str) -> Option<Self> {
+    match scheme {
+      "zip" => Some(Self::Zip),
+      "tar" => Some(Self::Tar),
+      "tgz" => Some(Self::TarGz),
+      "7z" => Some(Self::SevenZ),
+      _ => None,
+    }
+  }
+
+  /// the path without the archive extension, used as default extract directory
+  fn strip_ext(path: &str) -> &str {
+    let lower = path.to_lowercase();
+    [
+      ".tar.gz", ".tar.zst", ".tgz", ".tzst", ".tar", ".zip", ".7z",
+    ]
+    .iter()
+    .find(|ext| lower.ends_with(*ext))
+    .map(|ext| &path[..path.len() - ext.len()])
+    .unwrap_or(path)
+  }
+}
+
+/// a path point into an archive, eg: `zip://bundle.zip!/data.csv`
+pub(crate) struct EntryPath<'a> {
+  format: ArchiveFormat,
+  archive: &'a str,
+  entry: &'a str,
+}
+
+impl<'a> EntryPath<'a> {
+  pub(crate) fn parse(full: &'a str) -> Option<Self> {
+    let (scheme, rest) = full.split_once("://")?;
+    let scheme_format = ArchiveFormat::from_scheme(scheme)?;
+    let (archive, entry) = rest.split_once('!').unwrap_or((rest, ""));
+    let format = ArchiveFormat::from_path(archive).unwrap_or(scheme_format);
+    Some(Self {
+      format,
+      archive,
+      entry: entry.trim_start_matches('/'),
+    })
+  }
+}
+
+struct EntryInfo {
+  name: String,
+  is_dir: bool,
+  size: u64,
+  last_modified: Option<i64>,
+}
+
+impl EntryInfo {
+  fn to_json(&self) -> Value {
+    let name = self.name.trim_end_matches('/');
+    json!({
+      "path": self.name,
+      "name": name.rsplit('/').next().unwrap_or(name),
+      "isDir": self.is_dir,
+      "size": self.size,
+      "lastModified": self.last_modified,
+    })
+  }
+}
+
+/// options of the `EXTRACT` method
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ExtractOptions {
+  /// the directory to extract to, default is the archive path without extension
+  dest: Option<String>,
+  /// glob patterns of the entries to extract, default is all
+  files: Option<Vec<String>>,
+}
+
+/// handle `READ` and `LIST` of the path point into an archive
+pub(crate) async fn do_entry_action(
+  action: &FileAction,
+  path: EntryPath<'_>,
+) -> Result<FileActionResult> {
+  let data = read_bytes(path.archive, action.connection.as_ref()).await?;
+  let format = path.format;
+  let method = action.method.to_lowercase();
+  match method.as_str() {
+    "read" => {
+      if path.entry.is_empty() {
+        return Err(anyhow!("Missing entry name in archive path"));
+      }
+      let entry = path.entry.to_string();
+      let name = entry.clone();
+      let mut files =
+        tokio::task::spawn_blocking(move || read_entries(format, data, |n| n == name)).await??;
+      let (_, body) = files
+        .pop()
+        .ok_or(anyhow!("Entry '{}' not found in archive", entry))?;
+      let mimetype = action
+        .override_result_mimetype
+        .clone()
+        .unwrap_or(mimetype_from_ext(&entry));
+      bytes_to_json(body, mimetype, None)
+    }
+    "list" => {
+      let entries = tokio::task::spawn_blocking(move || list_entries(format, data)).await??;
+      let patterns = match split_glob(path.entry) {
+        (_, Some(_)) => compile_patterns(&[path.entry.to_string()])?,
+        _ => Vec::new(),
+      };
+      let prefix = path.entry.trim_end_matches('/');
+      let items = entries
+        .iter()
+        .filter(|e| {
+          let name = e.name.trim_end_matches('/');
+          if !patterns.is_empty() {
+            patterns.iter().any(|p| p.matches_with(name, MATCH_OPTIONS))
+          } else {
+            prefix.is_empty() || name.starts_with(&format!("{}/", prefix))
+          }
+        })
+        .map(EntryInfo::to_json)
+        .collect();
+      Ok(Value::Array(items))
+    }
+    _ => Err(anyhow!("Unsupported archive entry method: {}", method)),
+  }
+}
+
+/// extract the archive at `path`, return the path of extracted files
+pub(crate) async fn extract(
+  op: &Operator,
+  path: &str,
+  action: &FileAction,
+) -> Result<FileActionResult> {
+  let format =
+    ArchiveFormat::from_path(path).ok_or(anyhow!("Unsupported archive format: {}", path))?;
+  let options = action
+    .options
+    .as_ref()
+    .filter(|o| o.is_object())
+    .map(|o| serde_json::from_value::<ExtractOptions>(o.clone()))
+    .transpose()?
+    .unwrap_or_default();
+  let patterns = compile_patterns(options.files.as_deref().unwrap_or_default())?;
+
+  let data = op.read(path).await?.to_bytes();
+  let files = tokio::task::spawn_blocking(move || {
+    read_entries(format, data, |name| {
+      patterns.is_empty() || patterns.iter().any(|p| p.matches_with(name, MATCH_OPTIONS))
+    })
+  })
+  .await??;
+
+  let dest = options
+    .dest
+    .unwrap_or_else(|| ArchiveFormat::strip_ext(&action.path).to_string());
+  let (dest_op, dest_path) = path_operator(&dest, action.connection.as_ref())?;
+  let dest = dest.trim_end_matches('/');
+  let dest_path = dest_path.trim_end_matches('/');
+
+  let mut extracted = Vec::new();
+  for (name, body) in files {
+    let Some(name) = safe_entry_name(&name) else {
+      debug!(name, "skip unsafe archive entry");
+      continue;
+    };
+    dest_op
+      .write(&format!("{}/{}", dest_path, name), body)
+      .await?;
+    extracted.push(Value::String(format!("{}/{}", dest, name)));
+  }
+  Ok(Value::Array(extracted))
+}
+
+/// create the archive at `path` with the files in `action.body`
+pub(crate) async fn create(
+  op: &Operator,
+  path: &str,
+  action: &FileAction,
+) -> Result<FileActionResult> {
+  let format =
+    ArchiveFormat::from_path(path).ok_or(anyhow!("Unsupported archive format: {}", path))?;
+  let sources = match action.body.as_ref() {
+    Some(Value::Array(a)) => a.clone(),
+    Some(v) => vec![v.clone()],
+    None => return Err(anyhow!("No files to archive")),
+  };
+
+  let connection = action.connection.as_ref();
+  let mut files = Vec::new();
+  for source in sources {
+    match source {
+      Value::String(src) => files.extend(read_source(&src, None, connection).await?),
+      Value::Object(obj) => {
+        let name = obj.get("name").and_then(Value::as_str);
+        if let Some(src) = obj.get("path").and_then(Value::as_str) {
+          files.extend(read_source(src, name, connection).await?);
+        } else if let Some(content) = obj.get("content") {
+          let name = name.ok_or(anyhow!("Missing name of archive content"))?;
+          let body = to_mimetype_bytes(content, mimetype_from_ext(name))?;
+          files.push((name.to_string(), body));
+        } else {
+          return Err(anyhow!("Archive file should have 'path' or 'content'"));
+        }
+      }
+      _ => return Err(anyhow!("Invalid archive file: {}", source)),
+    }
+  }
+
+  let names = files
+    .iter()
+    .map(|(name, _)| Value::String(name.clone()))
+    .collect::<Vec<_>>();
+  let body = tokio::task::spawn_blocking(move || build_archive(format, files)).await??;
+  op.write(path, body).await?;
+
+  Ok(json!({
+    "path": action.path,
+    "files": names,
+  }))
+}
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>> {
+  patterns
+    .iter()
+    .flat_map(|p| expand_braces(p.trim_start_matches('/')))
+    .map(|p| Pattern::new(&p).map_err(Into::into))
+    .collect()
+}
+
+/// read the file or the files matched by the glob path, return them with the name in archive
+async fn read_source(
+  src: &str,
+  name: Option<&str>,
+  connection: Option<&Value>,
+) -> Result<Vec<(String, Bytes)>> {
+  let (op, path) = path_operator(src, connection)?;
+  if let (_, Some(_)) = split_glob(&path) {
+    let prefix = name.map(|n| n.trim_end_matches('/'));
+    let mut files = Vec::new();
+    for (file, rel) in glob_files(&op, &path).await? {
+      let body = op.read(&file).await?.to_bytes();
+      let name = match prefix {
+        Some(prefix) => format!("{}/{}", prefix, rel),
+        None => rel,
+      };
+      files.push((name, body));
+    }
+    Ok(files)
+  } else {
+    let body = op.read(&path).await?.to_bytes();
+    let name = name
+      .map(|n| n.to_string())
+      .unwrap_or_else(|| path.rsplit(['/', '\\']).next().unwrap_or(&path).to_string());
+    Ok(vec![(name, body)])
+  }
+}
+
+async fn read_bytes(path: &str, connection: Option<&Value>) -> Result<Bytes> {
+  let (op, path) = path_operator(path, connection)?;
+  Ok(op.read(&path).await?.to_bytes())
+}
+
+/// normalize the entry name, `None` if it escapes the extract directory
+fn safe_entry_name(name: &str) -> Option<String> {
+  let mut parts = Vec::new();
+  for part in name.split(['/', '\\']) {
+    match part {
+      "" | "." => continue,
+      ".." => return None,
+      p if p.contains(':') => return None,
+      p => parts.push(p),
+    }
+  }
+  if parts.is_empty() {
+    None
+  } else {
+    Some(parts.join("/"))
+  }
+}
+
+fn tar_reader(format: ArchiveFormat, data: Bytes) -> Result<tar::Archive<Box<dyn Read>>> {
+  let reader: Box<dyn Read> = match format {
+    ArchiveFormat::Tar => Box::new(Cursor::new(data)),
+    ArchiveFormat::TarGz => Box::new(flate2::read::GzDecoder::new(Cursor::new(data))),
+    ArchiveFormat::TarZst => Box::new(zstd::Decoder::new(Cursor::new(data))?),
+    _ => return Err(anyhow!("Not a tar archive")),
+  };
+  Ok(tar::Archive::new(reader))
+}
+
+fn list_entries(format: ArchiveFormat, data: Bytes) -> Result<Vec<EntryInfo>> {
+  let mut entries = Vec::new();
+  match format {
+    ArchiveFormat::Zip => {
+      let mut zip = zip::ZipArchive::new(Cursor::new(data))?;
+      for i in 0..zip.len() {
+        let file = zip.by_index(i)?;
+        entries.push(EntryInfo {
+          name: file.name()?.to_string(),
+          is_dir: file.is_dir(),
+          size: file.size(),
+          last_modified: file
+            .last_modified()
+            .and_then(|t| time::PrimitiveDateTime::try_from(t).ok())
+            .map(|t| t.assume_utc().unix_timestamp() * 1000),
+        });
+      }
+    }
+    ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+      let mut archive = tar_reader(format, data)?;
+      for entry in archive.entries()? {
+        let entry = entry?;
+        entries.push(EntryInfo {
+          name: entry.path()?.to_string_lossy().to_string(),
+          is_dir: entry.header().entry_type().is_dir(),
+          size: entry.size(),
+          last_modified: entry.header().mtime().ok().map(|t| t as i64 * 1000),
+        });
+      }
+    }
+    ArchiveFormat::SevenZ => {
+      let reader =
+        sevenz_rust2::ArchiveReader::new(Cursor::new(data), sevenz_rust2::Password::empty())?;
+      for file in reader.archive().files.iter() {
+        entries.push(EntryInfo {
+          name: file.name().to_string(),
+          is_dir: file.is_directory(),
+          size: file.size(),
+          last_modified: file
+            .has_last_modified_date
+            .then(|| std::time::SystemTime::from(file.last_modified_date()))
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64),
+        });
+      }
+    }
+  }
+  Ok(entries)
+}
+
+/// read the content of the files which name matched by `filter`
+fn read_entries<F: Fn(&str) -> bool>(
+  format: ArchiveFormat,
+  data: Bytes,
+  filter: F,
+) -> Result<Vec<(String, Bytes)>> {
+  let mut files = Vec::new();
+  match format {
+    ArchiveFormat::Zip => {
+      let mut zip = zip::ZipArchive::new(Cursor::new(data))?;
+      for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        let name = file.name()?.to_string();
+        if file.is_dir() || !filter(&name) {
+          continue;
+        }
+        let mut body = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut body)?;
+        files.push((name, body.into()));
+      }
+    }
+    ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+      let mut archive = tar_reader(format, data)?;
+      for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().to_string();
+        if !entry.header().entry_type().is_file() || !filter(&name) {
+          continue;
+        }
+        let mut body = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut body)?;
+        files.push((name, body.into()));
+      }
+    }
+    ArchiveFormat::SevenZ => {
+      let mut reader =
+        sevenz_rust2::ArchiveReader::new(Cursor::new(data), sevenz_rust2::Password::empty())?;
+      reader.for_each_entries(|entry, r| {
+        if !entry.is_directory() && filter(entry.name()) {
+          let mut body = Vec::with_capacity(entry.size() as usize);
+          r.read_to_end(&mut body)?;
+          files.push((entry.name().to_string(), body.into()));
+        }
+        Ok(true)
+      })?;
+    }
+  }
+  Ok(files)
+}
+
+fn build_tar<W: Write>(writer: W, files: Vec<(String, Bytes)>) -> Result<W> {
+  let now = std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or_default();
+  let mut builder = tar::Builder::new(writer);
+  for (name, body) in files {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(body.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(now);
+    builder.append_data(&mut header, name, body.as_ref())?;
+  }
+  Ok(builder.into_inner()?)
+}
+
+fn build_archive(format: ArchiveFormat, files: Vec<(String, Bytes)>) -> Result<Bytes> {
+  let data = match format {
+    ArchiveFormat::Zip => {
+      let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+      for (name, body) in files {
+        zip.start_file(name, zip::write::SimpleFileOptions::default())?;
+        zip.write_all(&body)?;
+      }
+      zip.finish()?.into_inner()
+    }
+    ArchiveFormat::Tar => build_tar(Vec::new(), files)?,
+    ArchiveFormat::TarGz => {
+      let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+      build_tar(encoder, files)?.finish()?
+    }
+    ArchiveFormat::TarZst => {
+      let encoder = zstd::Encoder::new(Vec::new(), 0)?;
+      build_tar(encoder, files)?.finish()?
+    }
+    ArchiveFormat::SevenZ => {
+      let mut writer = sevenz_rust2::ArchiveWriter::new(Cursor::new(Vec::new()))?;
+      for (name, body) in files {
+        writer.push_archive_entry(
+          sevenz_rust2::ArchiveEntry::new_file(&name),
+          Some(body.as_ref()),
+        )?;
+      }
+      writer.finish()?.into_inner()
+    }
+  };
+  Ok(data.into())
+}
@@ -0,0 +1,89 @@
+use a2a_types::Value;
+use anyhow::{anyhow, Result};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use sha2::Sha256;
+
+/// checksum options of `READ` and `WRITE` method, passed by `FileAction.options`
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct ChecksumOptions {
+  /// 'md5' or 'sha256', default is 'md5' when `expected_checksum` is set
+  checksum: Option<String>,
+  /// the expected digest of the content, in hex or base64
+  expected_checksum: Option<String>,
+}
+
+impl ChecksumOptions {
+  pub(crate) fn from_options(options: Option<&Value>) -> Result<Self> {
+    Ok(
+      options
+        .filter(|o| o.is_object())
+        .map(|o| serde_json::from_value::<Self>(o.clone()))
+        .transpose()?
+        .unwrap_or_default(),
+    )
+  }
+
+  /// verify the content if the expected checksum is set, return the digest when checksum is required
+  pub(crate) fn check(&self, content: &[u8], path: &str) -> Result<Option<String>> {
+    let checksum = match (self.checksum.as_ref(), self.expected_checksum.as_ref()) {
+      (None, None) => return Ok(None),
+      (Some(name), _) => Checksum::parse(name)?,
+      (None, Some(_)) => Checksum::Md5,
+    };
+    match self.expected_checksum.as_ref() {
+      Some(expected) => checksum.verify(content, expected, path).map(Some),
+      None => Ok(Some(checksum.digest(content))),
+    }
+  }
+}
+
+/// checksum algorithm of the file content
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Checksum {
+  Md5,
+  Sha256,
+}
+
+impl Checksum {
+  pub(crate) fn parse(name: &str) -> Result<Self> {
+    match name.to_lowercase().replace('-', "").as_str() {
+      "md5" => Ok(Self::Md5),
+      "sha256" => Ok(Self::Sha256),
+      _ => Err(anyhow!("Unsupported checksum: {}", name)),
+    }
+  }
+
+  /// hex digest of the content
+  pub(crate) fn digest(&self, content: &[u8]) -> String {
+    let bytes = match self {
+      Self::Md5 => Md5::digest(content).to_vec(),
+      Self::Sha256 => Sha256::digest(content).to_vec(),
+    };
+    hex_simd::encode_to_string(bytes, hex_simd::AsciiCase::Lower)
+  }
+
+  /// verify the content by the expected digest, in hex or base64
+  pub(crate) fn verify(&self, content: &[u8], expected: &str, path: &str) -> Result<String> {
+    let actual = self.digest(content);
+    let expected_hex = if expected.len() == actual.len() {
+      expected.to_lowercase()
+    } else {
+      base64_simd::STANDARD
+        .decode_to_vec(expected.as_bytes())
+        .map(|b| hex_simd::encode_to_string(b, hex_simd::AsciiCase::Lower))
+        .unwrap_or_default()
+    };
+    if actual != expected_hex {
+      return Err(anyhow!(
+        "Checksum mismatch of {}: expected {:?} {}, actual {}",
+        path,
+        self,
+        expected,
+        actual
+      ));
+    }
+    Ok(actual)
+  }
+}
@@ -0,0 +1,310 @@
+use a2a_types::{FileActionResult, Value};
+use anyhow::{anyhow, Result};
+use glob::{MatchOptions, Pattern};
+use opendal::{Entry, Operator};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use super::checksum::Checksum;
+
+/// options of the `LIST` method, passed by `FileAction.options`
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ListOptions {
+  /// sort by 'name', 'path', 'size' or 'lastModified'
+  sort_by: Option<String>,
+  /// sort in descending order
+  sort_desc: bool,
+  /// max number of entries to return, applied after filter and sort
+  limit: Option<usize>,
+  /// only entries modified at or after it, epoch milliseconds or RFC3339 string
+  modified_after: Option<Value>,
+  /// only entries modified before it, epoch milliseconds or RFC3339 string
+  modified_before: Option<Value>,
+  /// only entries with size >= min_size
+  min_size: Option<u64>,
+  /// only entries with size <= max_size
+  max_size: Option<u64>,
+  /// fill the eTag of the files without it by the md5 of the content, the same as the `WRITE` result,
+  /// it reads every listed file, so it's only done when asked
+  e_tag: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListItem {
+  pub(crate) path: String,
+  pub(crate) name: String,
+  pub(crate) is_dir: bool,
+  pub(crate) size: u64,
+  pub(crate) content_md5: Option<String>,
+  #[serde(rename = "eTag")]
+  pub(crate) etag: Option<String>,
+  pub(crate) last_modified: Option<i64>,
+}
+
+impl ListItem {
+  fn from_entry(entry: &Entry) -> Self {
+    let meta = entry.metadata();
+    Self {
+      path: entry.path().to_string(),
+      name: entry.name().to_string(),
+      is_dir: meta.is_dir(),
+      size: meta.content_length(),
+      content_md5: meta.content_md5().map(|s| s.to_string()),
+      etag: meta.etag().map(|s| s.to_string()),
+      last_modified: meta
+        .last_modified()
+        .map(|t| t.into_inner().as_millisecond()),
+    }
+  }
+
+  /// fill missing metadata, some services (eg: fs) only return entry mode in list
+  async fn fill_metadata(&mut self, op: &Operator) {
+    if self.is_dir || self.last_modified.is_some() {
+      return;
+    }
+    match op.stat(&self.path).await {
+      Ok(meta) => {
+        self.size = meta.content_length();
+        self.content_md5 = self
+          .content_md5
+          .take()
+          .or(meta.content_md5().map(|s| s.to_string()));
+        self.etag = self.etag.take().or(meta.etag().map(|s| s.to_string()));
+        self.last_modified = self.last_modified.or(
+          meta
+            .last_modified()
+            .map(|t| t.into_inner().as_millisecond()),
+        );
+      }
+      Err(err) => warn!(path = self.path, %err, "file list stat"),
+    }
+  }
+
+  pub(crate) fn to_json(&self) -> Value {
+    serde_json::to_value(self).unwrap_or_default()
+  }
+}
+
+pub(crate) async fn list(
+  op: &Operator,
+  path: &str,
+  options: Option<&Value>,
+) -> Result<FileActionResult> {
+  let options = options
+    .filter(|o| o.is_object())
+    .map(|o| serde_json::from_value::<ListOptions>(o.clone()))
+    .transpose()?
+    .unwrap_or_default();
+
+  let mut items = list_items(op, path).await?;
+
+  let modified_after = options
+    .modified_after
+    .as_ref()
+    .map(parse_timestamp)
+    .transpose()?;
+  let modified_before = options
+    .modified_before
+    .as_ref()
+    .map(parse_timestamp)
+    .transpose()?;
+
+  items.retain(|item| {
+    let in_time = match (modified_after, modified_before) {
+      (None, None) => true,
+      (after, before) => item
+        .last_modified
+        .map(|t| after.map(|a| t >= a).unwrap_or(true) && before.map(|b| t < b).unwrap_or(true))
+        .unwrap_or(false),
+    };
+    let in_size = match (options.min_size, options.max_size) {
+      (None, None) => true,
+      (min, max) => {
+        min.map(|m| item.size >= m).unwrap_or(true) && max.map(|m| item.size <= m).unwrap_or(true)
+      }
+    };
+    in_time && in_size
+  });
+
+  if let Some(sort_by) = options.sort_by.as_ref() {
+    match sort_by.as_str() {
+      "name" => items.sort_by(|a, b| a.name.cmp(&b.name)),
+      "path" => items.sort_by(|a, b| a.path.cmp(&b.path)),
+      "size" => items.sort_by_key(|item| item.size),
+      "lastModified" => items.sort_by_key(|item| item.last_modified),
+      _ => return Err(anyhow!("Unsupported sort field: {}", sort_by)),
+    }
+    if options.sort_desc {
+      items.reverse();
+    }
+  }
+
+  if let Some(limit) = options.limit {
+    items.truncate(limit);
+  }
+
+  if options.e_tag {
+    for item in items.iter_mut().filter(|i| !i.is_dir && i.etag.is_none()) {
+      let content = op.read(&item.path).await?.to_bytes();
+      item.etag = Some(Checksum::Md5.digest(&content));
+    }
+  }
+
+  Ok(Value::Array(items.iter().map(ListItem::to_json).collect()))
+}
+
+/// list the entries matched by the glob path with metadata
+pub(crate) async fn list_items(op: &Operator, path: &str) -> Result<Vec<ListItem>> {
+  let mut items = Vec::new();
+  for (entry, _) in glob_entries(op, path).await? {
+    let mut item = ListItem::from_entry(&entry);
+    item.fill_metadata(op).await;
+    items.push(item);
+  }
+  Ok(items)
+}
+
+/// list the entries matched by the glob path, with the path relative to the glob base,
+/// a path without any glob character just list the directory
+async fn glob_entries(op: &Operator, path: &str) -> Result<Vec<(Entry, String)>> {
+  let (base, pattern) = split_glob(path);
+  let patterns = match pattern.as_ref() {
+    Some(p) => expand_braces(p)
+      .iter()
+      .map(|p| Pattern::new(p))
+      .collect::<Result<Vec<_>, _>>()?,
+    None => Vec::new(),
+  };
+  let recursive = pattern.as_deref().map(is_recursive).unwrap_or(false);
+
+  debug!(base, ?pattern, recursive, "file list");
+
+  let match_options = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+  };
+  let base_rel = base.trim_start_matches('/');
+  let entries = op.list_with(&base).recursive(recursive).await?;
+  let mut matched = Vec::new();
+  for entry in entries.into_iter() {
+    let rel = entry
+      .path()
+      .strip_prefix(base_rel)
+      .unwrap_or(entry.path())
+      .trim_end_matches('/')
+      .to_string();
+    if !patterns.is_empty()
+      && (rel.is_empty() || !patterns.iter().any(|p| p.matches_with(&rel, match_options)))
+    {
+      continue;
+    }
+    matched.push((entry, rel));
+  }
+  Ok(matched)
+}
+
+/// files matched by the glob path, each is (path to the operator root, path relative to the glob base)
+pub(crate) async fn glob_files(op: &Operator, path: &str) -> Result<Vec<(String, String)>> {
+  Ok(
+    glob_entries(op, path)
+      .await?
+      .into_iter()
+      .filter(|(entry, _)| !entry.metadata().is_dir())
+      .map(|(entry, rel)| (entry.path().to_string(), rel))
+      .collect(),
+  )
+}
+
+/// only walk sub directories when the pattern spans multiple path segments
+pub(crate) fn is_recursive(pattern: &str) -> bool {
+  pattern.contains('/') || pattern.contains("**")
+}
+
+fn is_glob_segment(segment: &str) -> bool {
+  segment.contains(['*', '?', '[', '{'])
+}
+
+/// split the path to the directory to list and the glob pattern relative to it,
+/// eg: `data/2024-??-*.json` => (`data/`, `2024-??-*.json`)
+pub(crate) fn split_glob(path: &str) -> (String, Option<String>) {
+  let segments = path.split('/').collect::<Vec<_>>();
+  match segments.iter().position(|s| is_glob_segment(s)) {
+    Some(idx) => {
+      let mut base = segments[..idx].join("/");
+      if !base.is_empty() {
+        base.push('/');
+      }
+      (base, Some(segments[idx..].join("/")))
+    }
+    None => (path.to_string(), None),
+  }
+}
+
+/// expand `{a,b}` alternatives to multiple patterns, nested braces are supported
+pub(crate) fn expand_braces(pattern: &str) -> Vec<String> {
+  let Some(open) = pattern.find('{') else {
+    return vec![pattern.to_string()];
+  };
+
+  let mut depth = 0;
+  let mut close = None;
+  let mut splits = Vec::new();
+  for (i, c) in pattern[open..].char_indices() {
+    match c {
+      '{' => depth += 1,
+      '}' => {
+        depth -= 1;
+        if depth == 0 {
+          close = Some(open + i);
+          break;
+        }
+      }
+      ',' if depth == 1 => splits.push(open + i),
+      _ => {}
+    }
+  }
+
+  let Some(close) = close else {
+    // unbalanced brace, treat it literal
+    return vec![pattern.to_string()];
+  };
+
+  let prefix = &pattern[..open];
+  let suffix = &pattern[close + 1..];
+  let mut start = open + 1;
+  let mut alternatives = Vec::new();
+  for end in splits.into_iter().chain(std::iter::once(close)) {
+    alternatives.push(&pattern[start..end]);
+    start = end + 1;
+  }
+
+  alternatives
+    .into_iter()
+    .flat_map(|alt| expand_braces(&format!("{}{}{}", prefix, alt, suffix)))
+    .collect()
+}
+
+/// parse a timestamp to epoch milliseconds, accept number or RFC3339/date string
+fn parse_timestamp(value: &Value) -> Result<i64> {
+  use time::format_description::well_known::Rfc3339;
+  match value {
+    Value::Number(n) => n
+      .as_i64()
+      .or(n.as_f64().map(|f| f as i64))
+      .ok_or(anyhow!("Invalid timestamp: {}", n)),
+    Value::String(s) => {
+      if let Ok(t) = time::OffsetDateTime::parse(s, &Rfc3339) {
+        Ok((t.unix_timestamp_nanos() / 1_000_000) as i64)
+      } else {
+        let format = time::macros::format_description!("[year]-[month]-[day]");
+        let date =
+          time::Date::parse(s, &format).map_err(|_| anyhow!("Invalid timestamp: {}", s))?;
+        Ok((date.midnight().assume_utc().unix_timestamp_nanos() / 1_000_000) as i64)
+      }
+    }
+    _ => Err(anyhow!("Invalid timestamp: {}", value)),
+  }
+}
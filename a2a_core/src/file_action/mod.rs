@@ -1,10 +1,18 @@
 use std::{collections::HashMap, path, str::FromStr};
 
-use a2a_tojson::{bytes_to_json, to_mimetype_bytes};
-use a2a_types::{FileAction, FileActionResult};
+use a2a_tojson::bytes_to_json;
+use a2a_types::{FileAction, FileActionResult, Value};
 use anyhow::{anyhow, Result};
-use opendal::Scheme;
-use serde_json::json;
+use opendal::{Operator, Scheme};
+
+mod archive;
+mod checksum;
+mod list;
+mod presign;
+mod watch;
+mod write;
+
+pub use presign::{init_file_link, verify_file_link};
 
 fn split_schema_path(full: &str) -> (&str, String) {
   full
@@ -37,21 +45,18 @@ fn read_data_url(data_url: &str) -> Option<FileActionResult> {
   bytes_to_json(body.into(), mimetype, None).ok()
 }
 
-pub async fn do_action(action: FileAction) -> Result<FileActionResult> {
-  if action.method.eq_ignore_ascii_case("read") {
-    if let Some(value) = read_data_url(&action.path) {
-      return Ok(value);
-    }
-  }
-
-  let (schema, mut path) = split_schema_path(&action.path);
+/// build the operator for the full path(with schema), return it with the path relative to the operator root
+pub(crate) fn path_operator(full: &str, connection: Option<&Value>) -> Result<(Operator, String)> {
+  let (schema, mut path) = split_schema_path(full);
   let scheme = opendal::Scheme::from_str(schema)?;
-  let mut options = action
-    .connection
-    .and_then(|c| c.as_object().cloned())
+  let mut options = connection
+    .and_then(|c| c.as_object())
     .map(|m| {
-      m.into_iter()
-        .map(|(k, v)| (k, v.to_string()))
+      m.iter()
+        .map(|(k, v)| match v {
+          Value::String(s) => (k.clone(), s.clone()),
+          _ => (k.clone(), v.to_string()),
+        })
         .collect::<HashMap<_, _>>()
     })
     .unwrap_or_default();
@@ -70,72 +75,53 @@ pub async fn do_action(action: FileAction) -> Result<FileActionResult> {
     }
   }
 
-  let op = opendal::Operator::via_iter(scheme, options)?;
+  let op = Operator::via_iter(scheme, options)?;
+  Ok((op, path))
+}
+
+pub async fn do_action(action: FileAction) -> Result<FileActionResult> {
+  if action.method.eq_ignore_ascii_case("read") {
+    if let Some(value) = read_data_url(&action.path) {
+      return Ok(value);
+    }
+  }
+
+  if let Some(entry_path) = archive::EntryPath::parse(&action.path) {
+    return archive::do_entry_action(&action, entry_path).await;
+  }
+
+  let (op, path) = path_operator(&action.path, action.connection.as_ref())?;
 
   let method = action.method.to_lowercase();
 
   match method.as_str() {
     "read" => {
       let body = op.read(&path).await?.to_bytes();
+      checksum::ChecksumOptions::from_options(action.options.as_ref())?.check(&body, &path)?;
       let mimetype = action
         .override_result_mimetype
         .unwrap_or(mimetype_from_ext(&path));
-      bytes_to_json(body, mimetype, None)
-    }
-    "write" => {
-      if let Some(input) = action.body.as_ref() {
-        let mimetype = action
-          .override_result_mimetype
-          .unwrap_or(mimetype_from_ext(&path));
-        let body = to_mimetype_bytes(input, mimetype)?;
-        op.write(&path, body).await?;
+      // the same message as the received email
+      if mimetype == "message/rfc822" {
+        return crate::email_action::local::read_eml(&body, &path, action.options.as_ref()).await;
       }
-      Ok(serde_json::Value::Null)
+      bytes_to_json(body, mimetype, None)
     }
+    "write" => write::write(&op, &path, &action).await,
     "delete" => {
       op.delete(&path).await?;
       Ok(serde_json::Value::Null)
     }
-    "list" => {
-      let (path, recursive, pattern) = match path.as_str().find("**") {
-        Some(idx) => (
-          &path.as_str()[..idx],
-          true,
-          Some(glob::Pattern::new(&path.as_str()[idx..])?),
-        ),
-        None => (path.as_str(), false, None),
-      };
-
-      let items = op.list_with(path).recursive(recursive).await?;
-
-      let items = items
-        .into_iter()
-        .filter(|item| {
-          if let Some(pattern) = &pattern {
-            pattern.matches(item.path())
-          } else {
-            true
-          }
-        })
-        .map(|item| {
-          json!({
-            "path": item.path(),
-            "name": item.name(),
-            "isDir": item.metadata().is_dir(),
-            "size": item.metadata().content_length(),
-            "contentMd5": item.metadata().content_md5(),
-            "eTag": item.metadata().etag(),
-            "lastModified": item.metadata().last_modified().map(|t| t.into_inner().as_millisecond()),
-          })
-        })
-        .collect::<Vec<_>>();
-      Ok(json!(items))
-    }
+    "list" => list::list(&op, &path, action.options.as_ref()).await,
+    "extract" => archive::extract(&op, &path, &action).await,
+    "archive" => archive::create(&op, &path, &action).await,
+    "presign" => presign::presign(&op, &path, &action).await,
+    "watch" => watch::watch(&op, &path, &action).await,
     _ => Err(anyhow!("Unsupported file method: {}", method)),
   }
 }
 
-fn mimetype_from_ext(path: &str) -> String {
+pub(crate) fn mimetype_from_ext(path: &str) -> String {
   let ext = path
     .split('.')
     .last()
@@ -143,6 +129,7 @@ fn mimetype_from_ext(path: &str) -> String {
     .unwrap_or_default();
   let mimetype = match ext.as_str() {
     "json" => "application/json",
+    "ndjson" | "jsonl" => "application/ndjson",
     "txt" => "text/plain",
     "html" => "text/html",
     "xml" => "application/xml",
@@ -154,6 +141,7 @@ fn mimetype_from_ext(path: &str) -> String {
     "bmp" => "image/bmp",
     "tiff" => "image/tiff",
     "pdf" => "application/pdf",
+    "eml" => "message/rfc822",
     "doc" => "application/msword",
     "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
     "xls" => "application/vnd.ms-excel",
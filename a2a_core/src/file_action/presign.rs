@@ -0,0 +1,190 @@
+use std::{path::PathBuf, sync::OnceLock, time::Duration};
+
+use a2a_types::{FileAction, FileActionResult, Value};
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, KeyInit, Mac};
+use opendal::{
+  options::{ReadOptions, WriteOptions},
+  Operator,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+type HmacSha256 = Hmac<sha2::Sha256>;
+
+/// options of the `PRESIGN` method, passed by `FileAction.options`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct PresignOptions {
+  /// 'read' or 'write'
+  method: String,
+  /// seconds the url keeps valid
+  expires: u64,
+  /// content type of the download/upload
+  content_type: Option<String>,
+  /// content disposition of the download, eg: `attachment; filename="report.csv"`
+  content_disposition: Option<String>,
+}
+
+impl Default for PresignOptions {
+  fn default() -> Self {
+    Self {
+      method: "read".to_string(),
+      expires: 3600,
+      content_type: None,
+      content_disposition: None,
+    }
+  }
+}
+
+/// config of the signed local file links, set by `a2a serve` when it serves `/files/...`
+struct FileLinkConfig {
+  base_url: String,
+  secret: Vec<u8>,
+}
+
+static FILE_LINK: OnceLock<FileLinkConfig> = OnceLock::new();
+
+/// enable signed links of local files, `base_url` is the public url of the server serving `/files/...`
+pub fn init_file_link(base_url: &str, secret: &str) {
+  let _ = FILE_LINK.set(FileLinkConfig {
+    base_url: base_url.trim_end_matches('/').to_string(),
+    secret: secret.as_bytes().to_vec(),
+  });
+}
+
+/// verify the signature of a `/files/...` link, return the local file path when it's valid and not expired
+pub fn verify_file_link(link_path: &str, expires: i64, sig: &str) -> Option<PathBuf> {
+  let config = FILE_LINK.get()?;
+  if expires < time::OffsetDateTime::now_utc().unix_timestamp() {
+    return None;
+  }
+  let sig = hex_simd::decode_to_vec(sig.as_bytes()).ok()?;
+  let mut mac = HmacSha256::new_from_slice(&config.secret).ok()?;
+  mac.update(sign_content(link_path, expires).as_bytes());
+  mac.verify_slice(&sig).ok()?;
+
+  if cfg!(windows) {
+    Some(PathBuf::from(link_path))
+  } else {
+    Some(PathBuf::from(format!("/{}", link_path)))
+  }
+}
+
+fn sign_content(link_path: &str, expires: i64) -> String {
+  format!("{}\n{}", link_path, expires)
+}
+
+pub(crate) async fn presign(
+  op: &Operator,
+  path: &str,
+  action: &FileAction,
+) -> Result<FileActionResult> {
+  let options = action
+    .options
+    .as_ref()
+    .filter(|o| o.is_object())
+    .map(|o| serde_json::from_value::<PresignOptions>(o.clone()))
+    .transpose()?
+    .unwrap_or_default();
+
+  let expire = Duration::from_secs(options.expires);
+  let expires_at = time::OffsetDateTime::now_utc().unix_timestamp() + options.expires as i64;
+
+  if op.info().scheme() == "fs" {
+    return local_file_link(op, path, &options, expires_at);
+  }
+
+  let capability = op.info().full_capability();
+  let method = options.method.to_lowercase();
+  let req = match method.as_str() {
+    "read" if capability.presign_read => {
+      let read_options = ReadOptions {
+        override_content_type: options.content_type,
+        override_content_disposition: options.content_disposition,
+        ..Default::default()
+      };
+      op.presign_read_options(path, expire, read_options).await?
+    }
+    "write" if capability.presign_write => {
+      let write_options = WriteOptions {
+        content_type: options.content_type,
+        content_disposition: options.content_disposition,
+        ..Default::default()
+      };
+      op.presign_write_options(path, expire, write_options)
+        .await?
+    }
+    "read" | "write" => {
+      return Err(anyhow!(
+        "PRESIGN {} is not supported by {}",
+        method,
+        op.info().scheme()
+      ))
+    }
+    _ => return Err(anyhow!("Unsupported presign method: {}", options.method)),
+  };
+
+  let headers = req
+    .header()
+    .iter()
+    .map(|(k, v)| {
+      (
+        k.to_string(),
+        Value::String(v.to_str().unwrap_or_default().to_string()),
+      )
+    })
+    .collect::<serde_json::Map<_, _>>();
+
+  Ok(json!({
+    "url": req.uri().to_string(),
+    "method": req.method().as_str(),
+    "headers": headers,
+    "expiresAt": expires_at * 1000,
+  }))
+}
+
+/// sign a link of the local file, served by `a2a serve` at `/files/...`
+fn local_file_link(
+  op: &Operator,
+  path: &str,
+  options: &PresignOptions,
+  expires_at: i64,
+) -> Result<FileActionResult> {
+  if !options.method.eq_ignore_ascii_case("read") {
+    return Err(anyhow!("PRESIGN of local file only supports read"));
+  }
+  let config = FILE_LINK.get().ok_or(anyhow!(
+    "PRESIGN of local file requires `a2a serve` with --files-secret"
+  ))?;
+
+  let full = PathBuf::from(op.info().root()).join(path.trim_start_matches('/'));
+  let full = full
+    .canonicalize()
+    .map_err(|err| anyhow!("PRESIGN {}: {}", full.display(), err))?;
+  if !full.is_file() {
+    return Err(anyhow!("PRESIGN {}: not a file", full.display()));
+  }
+  let link_path = full
+    .to_string_lossy()
+    .replace('\\', "/")
+    .trim_start_matches('/')
+    .to_string();
+
+  let mut mac = HmacSha256::new_from_slice(&config.secret)?;
+  mac.update(sign_content(&link_path, expires_at).as_bytes());
+  let sig = hex_simd::encode_to_string(mac.finalize().into_bytes(), hex_simd::AsciiCase::Lower);
+
+  let encoded = link_path
+    .split('/')
+    .map(|s| urlencoding::encode(s).into_owned())
+    .collect::<Vec<_>>()
+    .join("/");
+
+  Ok(json!({
+    "url": format!("{}/files/{}?expires={}&sig={}", config.base_url, encoded, expires_at, sig),
+    "method": "GET",
+    "headers": {},
+    "expiresAt": expires_at * 1000,
+  }))
+}
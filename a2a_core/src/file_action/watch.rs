@@ -0,0 +1,246 @@
+use std::{
+  collections::BTreeMap,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use a2a_types::{FileAction, FileActionResult};
+use anyhow::{anyhow, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use opendal::Operator;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::{debug, warn};
+
+use super::{
+  checksum::Checksum,
+  list::{is_recursive, list_items, split_glob, ListItem},
+};
+
+/// options of the `WATCH` method, passed by `FileAction.options`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct WatchOptions {
+  /// local file to persist the state, default is in the temp dir keyed by the path
+  state: Option<String>,
+  /// seconds to wait for changes when nothing changed since the cursor
+  wait: u64,
+  /// seconds between listings when waiting for changes of object stores
+  poll_interval: u64,
+  /// don't report the existing entries on the first watch
+  skip_existing: bool,
+}
+
+impl Default for WatchOptions {
+  fn default() -> Self {
+    Self {
+      state: None,
+      wait: 0,
+      poll_interval: 10,
+      skip_existing: false,
+    }
+  }
+}
+
+/// the snapshot of the watched files, persisted between watches
+#[derive(Debug, Serialize, Deserialize)]
+struct WatchState {
+  path: String,
+  /// epoch milliseconds of the snapshot
+  cursor: i64,
+  entries: BTreeMap<String, ListItem>,
+}
+
+#[derive(Debug, Default)]
+struct Changes {
+  created: Vec<ListItem>,
+  modified: Vec<ListItem>,
+  deleted: Vec<ListItem>,
+}
+
+impl Changes {
+  fn diff(prev: &BTreeMap<String, ListItem>, current: &BTreeMap<String, ListItem>) -> Self {
+    let mut changes = Self::default();
+    for (path, item) in current {
+      match prev.get(path) {
+        None => changes.created.push(item.clone()),
+        Some(old)
+          if old.size != item.size
+            || old.last_modified != item.last_modified
+            || old.etag != item.etag =>
+        {
+          changes.modified.push(item.clone())
+        }
+        _ => {}
+      }
+    }
+    for (path, item) in prev {
+      if !current.contains_key(path) {
+        changes.deleted.push(item.clone());
+      }
+    }
+    changes
+  }
+
+  fn is_empty(&self) -> bool {
+    self.created.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+  }
+}
+
+pub(crate) async fn watch(
+  op: &Operator,
+  path: &str,
+  action: &FileAction,
+) -> Result<FileActionResult> {
+  let options = action
+    .options
+    .as_ref()
+    .filter(|o| o.is_object())
+    .map(|o| serde_json::from_value::<WatchOptions>(o.clone()))
+    .transpose()?
+    .unwrap_or_default();
+
+  let state_path = options
+    .state
+    .as_ref()
+    .map(PathBuf::from)
+    .unwrap_or_else(|| default_state_path(&action.path));
+  let prev_state = load_state(&state_path)?;
+
+  // watch local changes before the snapshot, so no change is missed while waiting
+  let mut fs_watcher = if options.wait > 0 && op.info().scheme() == "fs" {
+    Some(FsWatcher::new(op, path)?)
+  } else {
+    None
+  };
+
+  let mut current = snapshot(op, path).await?;
+  let since = prev_state.as_ref().map(|s| s.cursor);
+  let prev = match prev_state {
+    Some(state) => state.entries,
+    None if options.skip_existing => current.clone(),
+    None => BTreeMap::new(),
+  };
+  let mut changes = Changes::diff(&prev, &current);
+
+  let deadline = tokio::time::Instant::now() + Duration::from_secs(options.wait);
+  while changes.is_empty() && tokio::time::Instant::now() < deadline {
+    let remaining = deadline - tokio::time::Instant::now();
+    match fs_watcher.as_mut() {
+      Some(watcher) => watcher.wait(remaining).await,
+      None => {
+        tokio::time::sleep(remaining.min(Duration::from_secs(options.poll_interval.max(1)))).await
+      }
+    }
+    current = snapshot(op, path).await?;
+    changes = Changes::diff(&prev, &current);
+  }
+
+  let cursor = time::OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+  let state = WatchState {
+    path: action.path.clone(),
+    cursor: cursor as i64,
+    entries: current,
+  };
+  save_state(&state_path, &state)?;
+
+  let to_json = |items: &[ListItem]| items.iter().map(ListItem::to_json).collect::<Vec<_>>();
+  Ok(json!({
+    "created": to_json(&changes.created),
+    "modified": to_json(&changes.modified),
+    "deleted": to_json(&changes.deleted),
+    "since": since,
+    "cursor": state.cursor,
+  }))
+}
+
+/// the files matched by the path, keyed by the path
+async fn snapshot(op: &Operator, path: &str) -> Result<BTreeMap<String, ListItem>> {
+  Ok(
+    list_items(op, path)
+      .await?
+      .into_iter()
+      .filter(|item| !item.is_dir)
+      .map(|item| (item.path.clone(), item))
+      .collect(),
+  )
+}
+
+fn default_state_path(path: &str) -> PathBuf {
+  let key = Checksum::Md5.digest(path.as_bytes());
+  std::env::temp_dir()
+    .join("a2a_watch")
+    .join(format!("{}.json", key))
+}
+
+fn load_state(state_path: &Path) -> Result<Option<WatchState>> {
+  match std::fs::read(state_path) {
+    Ok(content) => match serde_json::from_slice::<WatchState>(&content) {
+      Ok(state) => Ok(Some(state)),
+      Err(err) => {
+        warn!(?state_path, %err, "invalid watch state, start over");
+        Ok(None)
+      }
+    },
+    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(anyhow!(
+      "Read watch state {}: {}",
+      state_path.display(),
+      err
+    )),
+  }
+}
+
+/// write to a temp file then rename, a crashed job never leaves a broken state
+fn save_state(state_path: &Path, state: &WatchState) -> Result<()> {
+  if let Some(dir) = state_path.parent() {
+    std::fs::create_dir_all(dir)?;
+  }
+  let tmp = state_path.with_extension("tmp");
+  std::fs::write(&tmp, serde_json::to_vec(state)?)?;
+  std::fs::rename(&tmp, state_path)?;
+  Ok(())
+}
+
+/// watch the local directory by the os notification(inotify on linux)
+struct FsWatcher {
+  _watcher: RecommendedWatcher,
+  rx: UnboundedReceiver<()>,
+}
+
+impl FsWatcher {
+  fn new(op: &Operator, path: &str) -> Result<Self> {
+    let (base, pattern) = split_glob(path);
+    let dir = PathBuf::from(op.info().root()).join(base.trim_start_matches('/'));
+    let mode = if pattern.as_deref().map(is_recursive).unwrap_or(false) {
+      RecursiveMode::Recursive
+    } else {
+      RecursiveMode::NonRecursive
+    };
+    debug!(?dir, ?mode, "file watch");
+
+    let (tx, rx) = unbounded_channel();
+    let mut watcher =
+      notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+          let _ = tx.send(());
+        }
+        Ok(_) => {}
+        Err(err) => warn!(%err, "file watch"),
+      })?;
+    watcher.watch(&dir, mode)?;
+    Ok(Self {
+      _watcher: watcher,
+      rx,
+    })
+  }
+
+  /// wait for the next change, a burst of events is merged into one
+  async fn wait(&mut self, timeout: Duration) {
+    if let Ok(Some(_)) = tokio::time::timeout(timeout, self.rx.recv()).await {
+      tokio::time::sleep(Duration::from_millis(200)).await;
+      while self.rx.try_recv().is_ok() {}
+    }
+  }
+}
@@ -0,0 +1,114 @@
+use a2a_tojson::to_mimetype_bytes;
+use a2a_types::{FileAction, FileActionResult, Value};
+use anyhow::{anyhow, Result};
+use opendal::{ErrorKind, Operator};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::debug;
+
+use super::{
+  checksum::{Checksum, ChecksumOptions},
+  mimetype_from_ext,
+};
+
+/// conditional options of the `WRITE` method, passed by `FileAction.options`
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct ConditionOptions {
+  /// only write when the file doesn't exist
+  if_not_exists: bool,
+  /// only write when the eTag of the file matches it
+  if_match: Option<String>,
+}
+
+pub(crate) async fn write(
+  op: &Operator,
+  path: &str,
+  action: &FileAction,
+) -> Result<FileActionResult> {
+  let Some(input) = action.body.as_ref() else {
+    return Ok(Value::Null);
+  };
+  let options = action.options.as_ref().filter(|o| o.is_object());
+  let condition = options
+    .map(|o| serde_json::from_value::<ConditionOptions>(o.clone()))
+    .transpose()?
+    .unwrap_or_default();
+  let checksum = ChecksumOptions::from_options(options)?;
+
+  let mimetype = action
+    .override_result_mimetype
+    .clone()
+    .unwrap_or(mimetype_from_ext(path));
+  let body = to_mimetype_bytes(input, mimetype)?;
+  let digest = checksum.check(&body, path)?;
+
+  // services without native support are checked before write, which is not atomic
+  let capability = op.info().full_capability();
+  let mut write_options = opendal::options::WriteOptions::default();
+  if condition.if_not_exists {
+    if capability.write_with_if_not_exists {
+      write_options.if_not_exists = true;
+    } else if op.exists(path).await? {
+      return Err(anyhow!("Write conflict: {} already exists", path));
+    }
+  }
+  if let Some(etag) = condition.if_match.as_ref() {
+    if capability.write_with_if_match {
+      write_options.if_match = Some(etag.clone());
+    } else {
+      let current = current_etag(op, path).await?;
+      if current.as_deref().map(trim_etag) != Some(trim_etag(etag)) {
+        return Err(anyhow!(
+          "Write conflict: eTag of {} is {:?}, doesn't match {}",
+          path,
+          current,
+          etag
+        ));
+      }
+    }
+  }
+
+  let size = body.len();
+  let local_etag = Checksum::Md5.digest(&body);
+  let meta = op
+    .write_options(path, body, write_options)
+    .await
+    .map_err(|err| match err.kind() {
+      ErrorKind::ConditionNotMatch if condition.if_not_exists => {
+        anyhow!("Write conflict: {} already exists", path)
+      }
+      ErrorKind::ConditionNotMatch => anyhow!(
+        "Write conflict: eTag of {} doesn't match {}",
+        path,
+        condition.if_match.as_deref().unwrap_or_default()
+      ),
+      _ => err.into(),
+    })?;
+
+  Ok(json!({
+    "path": path,
+    "size": size,
+    "eTag": meta.etag().map(|s| s.to_string()).unwrap_or(local_etag),
+    "checksum": digest,
+  }))
+}
+
+/// eTag of the file, the md5 of the content for services without eTag(eg: fs)
+async fn current_etag(op: &Operator, path: &str) -> Result<Option<String>> {
+  let meta = match op.stat(path).await {
+    Ok(meta) => meta,
+    Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+    Err(err) => return Err(err.into()),
+  };
+  if let Some(etag) = meta.etag() {
+    return Ok(Some(etag.to_string()));
+  }
+  debug!(path, "no eTag, use md5 of the content");
+  let content = op.read(path).await?.to_bytes();
+  Ok(Some(Checksum::Md5.digest(&content)))
+}
+
+fn trim_etag(etag: &str) -> &str {
+  etag.trim_start_matches("W/").trim_matches('"')
+}
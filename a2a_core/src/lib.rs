@@ -17,6 +17,9 @@ mod shell_action;
 mod sql_action;
 pub mod utils;
 
+pub use email_action::watch_email;
+pub use file_action::{init_file_link, verify_file_link};
+
 pub async fn do_action(action: Action) -> Result<Value> {
   let id = uuid_v7();
   if tracing::enabled!(tracing::Level::TRACE) {
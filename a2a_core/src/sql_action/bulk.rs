@@ -0,0 +1,376 @@
+use a2a_types::{SqlAction, Value};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use super::{
+  encode::data_url_bytes,
+  lexer::{tokenize, Token},
+};
+
+/// the max rows of a multi-row `VALUES` statement
+const MAX_CHUNK_ROWS: usize = 1000;
+
+/// how the batch rows of an `INSERT` are loaded
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum BulkMode {
+  /// chunked multi-row `VALUES`
+  #[default]
+  Values,
+  /// postgres `COPY ... FROM STDIN`, mysql `LOAD DATA LOCAL` is not supported by the driver
+  Copy,
+  /// run the statement for each row
+  Off,
+}
+
+/// insert or update the rows conflict with the keys, `SqlAction.upsert`
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Upsert {
+  /// the columns of the primary key or unique index
+  keys: Vec<String>,
+  /// the columns to update, default is all inserted columns except the keys, empty to ignore the conflict
+  update: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BulkOptions {
+  pub mode: BulkMode,
+  pub upsert: Option<Upsert>,
+}
+
+impl BulkOptions {
+  pub(crate) fn from_action(action: &SqlAction) -> Result<Self> {
+    let mode = match action.bulk.as_deref() {
+      None | Some("values") => BulkMode::Values,
+      Some("copy") => BulkMode::Copy,
+      Some("off") => BulkMode::Off,
+      Some(other) => anyhow::bail!(
+        "Unsupported bulk `{}`, use `values`, `copy` or `off`",
+        other
+      ),
+    };
+    let upsert = action
+      .upsert
+      .as_ref()
+      .map(|u| serde_json::from_value::<Upsert>(u.clone()))
+      .transpose()
+      .map_err(|err| anyhow!("Invalid upsert: {}", err))?;
+    Ok(Self { mode, upsert })
+  }
+}
+
+/// the `INSERT ... VALUES (...)` statement with a single row,
+/// eg: `INSERT INTO t (a, b) VALUES (?, ?) RETURNING id`
+#[derive(Debug, PartialEq)]
+pub(crate) struct InsertStatement {
+  /// the sql before `VALUES`, eg: `INSERT INTO t (a, b) `
+  head: String,
+  /// the table after `INTO`, eg: `t`
+  table: Option<String>,
+  /// the column list after the table, eg: `["a", "b"]`
+  columns: Vec<String>,
+  /// the values of a row with the placeholders, eg: `(?, ?)`
+  tuple: String,
+  /// the count of placeholders in the tuple
+  params: usize,
+  /// the sql after the tuple, eg: ` RETURNING id`
+  tail: String,
+  /// `REPLACE INTO` of mysql
+  replace: bool,
+}
+
+impl InsertStatement {
+  /// `None` if it is not a single row `INSERT ... VALUES`
+  pub(crate) fn parse(sql: &str, backslash_escapes: bool) -> Option<Self> {
+    let tokens = tokenize(sql, backslash_escapes);
+    let first = tokens.iter().find(|t| t.is_significant())?;
+    if !first.is_word("INSERT") && !first.is_word("REPLACE") {
+      return None;
+    }
+
+    let mut depth = 0;
+    let mut values = None;
+    for (i, token) in tokens.iter().enumerate() {
+      match token {
+        Token::Other(s) if s == "(" => depth += 1,
+        Token::Other(s) if s == ")" => depth -= 1,
+        _ if depth == 0 && (token.is_word("VALUES") || token.is_word("VALUE")) => {
+          values = Some(i);
+          break;
+        }
+        _ => {}
+      }
+    }
+    let values = values?;
+
+    // the tuple in the parentheses after `VALUES`
+    let open = values
+      + 1
+      + tokens[values + 1..]
+        .iter()
+        .position(|t| t.is_significant())?;
+    if tokens[open] != Token::Other("(".to_string()) {
+      return None;
+    }
+    let close = matching_paren(&tokens, open)?;
+    let tail = &tokens[close + 1..];
+    // a multi-row `VALUES` already
+    if tail
+      .iter()
+      .find(|t| t.is_significant())
+      .is_some_and(|t| t.text() == ",")
+    {
+      return None;
+    }
+
+    let head = &tokens[..values];
+    let into = head.iter().position(|t| t.is_word("INTO"));
+    let (table, columns) = match into {
+      Some(into) => {
+        let rest = &head[into + 1..];
+        let paren = rest.iter().position(|t| t.text() == "(");
+        let table = join(&rest[..paren.unwrap_or(rest.len())]);
+        let columns = match paren {
+          Some(p) => {
+            let close = matching_paren(rest, p)?;
+            split_commas(&rest[p + 1..close])
+          }
+          None => vec![],
+        };
+        (Some(table.trim().to_string()), columns)
+      }
+      None => (None, vec![]),
+    };
+
+    Some(Self {
+      head: join(head),
+      table,
+      columns,
+      tuple: join(&tokens[open..=close]),
+      params: tokens[open..=close]
+        .iter()
+        .filter(|t| **t == Token::Param)
+        .count(),
+      tail: join(tail),
+      replace: first.is_word("REPLACE"),
+    })
+  }
+
+  /// the count of bind parameters of a row
+  pub(crate) fn params(&self) -> usize {
+    self.params
+  }
+
+  /// the rows of a chunk, limited by the max bind parameters of the database
+  pub(crate) fn chunk_rows(&self, max_params: usize) -> usize {
+    (max_params / self.params.max(1)).clamp(1, MAX_CHUNK_ROWS)
+  }
+
+  /// the statement with the rows of values and the conflict clause
+  pub(crate) fn to_sql(&self, rows: usize, conflict: &str) -> String {
+    let tuples = vec![self.tuple.as_str(); rows].join(", ");
+    format!("{}VALUES {}{}{}", self.head, tuples, conflict, self.tail)
+  }
+
+  /// the columns to check and update on conflict, they must be in the column list of the statement
+  pub(crate) fn upsert_columns<'a>(
+    &'a self,
+    upsert: &Upsert,
+  ) -> Result<(Vec<&'a str>, Vec<&'a str>)> {
+    if self.columns.is_empty() {
+      anyhow::bail!("upsert needs the columns, eg: `INSERT INTO t (id, name) VALUES (?, ?)`");
+    }
+    let column = |name: &str| {
+      self
+        .columns
+        .iter()
+        .find(|c| unquote(c).eq_ignore_ascii_case(unquote(name)))
+        .map(|c| c.as_str())
+        .ok_or_else(|| anyhow!("upsert column `{}` is not inserted", name))
+    };
+    let keys = upsert
+      .keys
+      .iter()
+      .map(|k| column(k))
+      .collect::<Result<Vec<_>>>()?;
+    if keys.is_empty() {
+      anyhow::bail!("upsert needs the conflict keys");
+    }
+    let update = match upsert.update.as_ref() {
+      Some(update) => update
+        .iter()
+        .map(|c| column(c))
+        .collect::<Result<Vec<_>>>()?,
+      None => self
+        .columns
+        .iter()
+        .map(|c| c.as_str())
+        .filter(|c| !keys.contains(c))
+        .collect(),
+    };
+    Ok((keys, update))
+  }
+
+  /// the `COPY ... FROM STDIN` of postgres, the tuple should be the placeholders of the columns
+  pub(crate) fn copy_sql(&self) -> Result<String> {
+    let tuple = tokenize(&self.tuple, false);
+    let plain = tuple
+      .iter()
+      .filter(|t| t.is_significant())
+      .all(|t| *t == Token::Param || matches!(t.text(), "(" | ")" | ","));
+    match self.table.as_ref() {
+      Some(table)
+        if plain
+          && self.params == self.columns.len()
+          && self.tail.trim().is_empty()
+          && !self.replace =>
+      {
+        Ok(format!(
+          "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+          table,
+          self.columns.join(", ")
+        ))
+      }
+      _ => anyhow::bail!(
+        "bulk copy needs a plain insert, eg: `INSERT INTO t (id, name) VALUES (?, ?)`"
+      ),
+    }
+  }
+}
+
+/// the conflict clause of postgres and sqlite
+pub(crate) fn on_conflict(keys: &[&str], update: &[&str]) -> String {
+  if update.is_empty() {
+    format!(" ON CONFLICT ({}) DO NOTHING", keys.join(", "))
+  } else {
+    let set = update
+      .iter()
+      .map(|c| format!("{} = EXCLUDED.{}", c, c))
+      .collect::<Vec<_>>();
+    format!(
+      " ON CONFLICT ({}) DO UPDATE SET {}",
+      keys.join(", "),
+      set.join(", ")
+    )
+  }
+}
+
+/// the row of `COPY ... WITH (FORMAT csv)`, NULL is the unquoted empty field,
+/// the data url is the hex of bytea, the objects and arrays are json
+pub(crate) fn copy_csv_row(row: &[Value], buffer: &mut Vec<u8>) {
+  for (i, value) in row.iter().enumerate() {
+    if i > 0 {
+      buffer.push(b',');
+    }
+    let text = match value {
+      Value::Null => continue,
+      Value::String(s) => match data_url_bytes(s) {
+        Some(bytes) => format!(
+          "\\x{}",
+          hex_simd::encode_to_string(bytes, hex_simd::AsciiCase::Lower)
+        ),
+        None => s.clone(),
+      },
+      _ => value.to_string(),
+    };
+    buffer.push(b'"');
+    buffer.extend_from_slice(text.replace('"', "\"\"").as_bytes());
+    buffer.push(b'"');
+  }
+  buffer.push(b'\n');
+}
+
+fn join(tokens: &[Token]) -> String {
+  tokens.iter().map(|t| t.text()).collect()
+}
+
+fn matching_paren(tokens: &[Token], open: usize) -> Option<usize> {
+  let mut depth = 0;
+  for (i, token) in tokens.iter().enumerate().skip(open) {
+    match token.text() {
+      "(" => depth += 1,
+      ")" => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(i);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+/// the trimmed items separated by the commas at the top level
+fn split_commas(tokens: &[Token]) -> Vec<String> {
+  tokens
+    .split(|t| t.text() == ",")
+    .map(|item| join(item).trim().to_string())
+    .filter(|item| !item.is_empty())
+    .collect()
+}
+
+fn unquote(name: &str) -> &str {
+  name.trim_matches(|c| c == '"' || c == '`')
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_insert_statement() {
+    let insert = InsertStatement::parse(
+      "INSERT INTO t (id, \"name\") VALUES (?, lower(?)) RETURNING id",
+      false,
+    )
+    .unwrap();
+    assert_eq!(insert.table.as_deref(), Some("t"));
+    assert_eq!(insert.columns, vec!["id", "\"name\""]);
+    assert_eq!(insert.params(), 2);
+    assert_eq!(
+      insert.to_sql(2, ""),
+      "INSERT INTO t (id, \"name\") VALUES (?, lower(?)), (?, lower(?)) RETURNING id"
+    );
+    assert!(insert.copy_sql().is_err());
+
+    let upsert = Upsert {
+      keys: vec!["ID".to_string()],
+      update: None,
+    };
+    let (keys, update) = insert.upsert_columns(&upsert).unwrap();
+    assert_eq!(
+      on_conflict(&keys, &update),
+      " ON CONFLICT (id) DO UPDATE SET \"name\" = EXCLUDED.\"name\""
+    );
+
+    let insert = InsertStatement::parse("insert into s.t (a, b) values (?, ?)", false).unwrap();
+    assert_eq!(
+      insert.copy_sql().unwrap(),
+      "COPY s.t (a, b) FROM STDIN WITH (FORMAT csv)"
+    );
+    assert_eq!(insert.chunk_rows(65535), 1000);
+    assert_eq!(insert.chunk_rows(999), 499);
+
+    assert!(InsertStatement::parse("INSERT INTO t VALUES (1), (2)", false).is_none());
+    assert!(InsertStatement::parse("INSERT INTO t SELECT * FROM s", false).is_none());
+    assert!(InsertStatement::parse("UPDATE t SET a = 'VALUES (?)'", false).is_none());
+
+    let mut buffer = vec![];
+    copy_csv_row(
+      &[
+        json!(1),
+        Value::Null,
+        json!(""),
+        json!("a\"b"),
+        json!({"k": 1}),
+      ],
+      &mut buffer,
+    );
+    assert_eq!(
+      String::from_utf8(buffer).unwrap(),
+      "\"1\",,\"\",\"a\"\"b\",\"{\"\"k\"\":1}\"\n"
+    );
+  }
+}
@@ -0,0 +1,190 @@
+use a2a_types::{SqlAction, SqlActionResult, Value};
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
+use serde_json::json;
+use tracing::{debug, warn};
+
+use super::{
+  bulk::{BulkMode, BulkOptions, InsertStatement},
+  decode::DecodeOptions,
+  lexer::returns_rows,
+  runner::{
+    action_result, action_statements, insert_rows, positional_rows, SqlMethod, StatementResult,
+  },
+  schema::{schema_tables, SchemaQueries},
+};
+
+/// the database client not based on sqlx, eg: mssql over TDS and clickhouse over HTTP,
+/// the statements are run in the same way as the sqlx databases
+pub(crate) trait SqlClient: Send {
+  /// the name of the database in the error messages
+  const NAME: &'static str;
+
+  /// the string literals escape quote by backslash, eg: `'it\'s'`
+  const BACKSLASH_ESCAPES: bool = false;
+
+  /// the max bind parameters of a statement
+  const MAX_PARAMS: usize;
+
+  /// the queries of the `schema` method
+  const SCHEMA: SchemaQueries;
+
+  /// the database supports transactions
+  const TRANSACTIONS: bool = true;
+
+  /// run the sql with the positional parameters `?`
+  fn execute<'c>(
+    &'c mut self,
+    sql: &'c str,
+    params: &'c [Value],
+    options: &'c DecodeOptions,
+  ) -> BoxFuture<'c, Result<StatementResult>>;
+
+  fn begin(&mut self) -> BoxFuture<'_, Result<()>> {
+    Box::pin(async { anyhow::bail!("transaction is not supported by {}", Self::NAME) })
+  }
+
+  fn commit(&mut self) -> BoxFuture<'_, Result<()>> {
+    Box::pin(async { anyhow::bail!("transaction is not supported by {}", Self::NAME) })
+  }
+
+  fn rollback(&mut self) -> BoxFuture<'_, Result<()>> {
+    Box::pin(async { anyhow::bail!("transaction is not supported by {}", Self::NAME) })
+  }
+}
+
+/// run the action by the client, same as `run_action` except `saveTo`, bulk copy and upsert,
+/// the database without transactions runs the statements one by one
+pub(crate) async fn run_client_action<C: SqlClient>(
+  client: &mut C,
+  action: &SqlAction,
+) -> Result<SqlActionResult> {
+  if SqlMethod::from_action(action)? == SqlMethod::Schema {
+    return client_schema(client).await;
+  }
+
+  let options = DecodeOptions::from_action(action)?;
+  if action.save_to.is_some() {
+    anyhow::bail!("saveTo is not supported by {}", C::NAME);
+  }
+  let bulk = match action.statements {
+    Some(_) => BulkOptions::default(),
+    None => BulkOptions::from_action(action)?,
+  };
+  if bulk.mode == BulkMode::Copy {
+    anyhow::bail!("bulk copy is only supported by postgres");
+  }
+  if bulk.upsert.is_some() {
+    anyhow::bail!("upsert is not supported by {}", C::NAME);
+  }
+
+  let (statements, transaction) = action_statements(action);
+  if !C::TRANSACTIONS && action.transaction == Some(true) {
+    anyhow::bail!("transaction is not supported by {}", C::NAME);
+  }
+  let results = if transaction && C::TRANSACTIONS {
+    client.begin().await?;
+    match run_statements(client, &statements, &options, bulk.mode, true).await {
+      Ok(results) => {
+        client.commit().await?;
+        results
+      }
+      Err(err) => {
+        debug!(%err, "rollback sql transaction");
+        // the rollback failure should not hide the error of the statements
+        if let Err(rollback_err) = client.rollback().await {
+          warn!(%err, %rollback_err, "rollback sql transaction failed");
+        }
+        return Err(err);
+      }
+    }
+  } else {
+    run_statements(client, &statements, &options, bulk.mode, false).await?
+  };
+  Ok(action_result(action, results))
+}
+
+async fn run_statements<C: SqlClient>(
+  client: &mut C,
+  statements: &[(&str, Option<&Value>)],
+  options: &DecodeOptions,
+  mode: BulkMode,
+  in_transaction: bool,
+) -> Result<Vec<StatementResult>> {
+  let mut results = Vec::new();
+  for (i, (query, rows)) in statements.iter().enumerate() {
+    let mut result = run_rows(client, query, *rows, options, mode, in_transaction)
+      .await
+      .map_err(|err| anyhow!("statement {} failed: {}", i, err))?;
+    result.returns_rows = returns_rows(query);
+    results.push(result);
+  }
+  Ok(results)
+}
+
+async fn run_rows<C: SqlClient>(
+  client: &mut C,
+  query: &str,
+  rows: Option<&Value>,
+  options: &DecodeOptions,
+  mode: BulkMode,
+  in_transaction: bool,
+) -> Result<StatementResult> {
+  let (positional, rows) = positional_rows(query, rows, C::BACKSLASH_ESCAPES)?;
+  if rows.is_empty() {
+    return client.execute(&positional, &[], options).await;
+  }
+
+  let insert = InsertStatement::parse(&positional, C::BACKSLASH_ESCAPES);
+  if let Some(insert) = insert.filter(|_| mode == BulkMode::Values && rows.len() > 1) {
+    let rows = insert_rows(&insert, &rows)?;
+    // the chunks are inserted in a transaction, unless the statements are in one
+    let transaction = C::TRANSACTIONS && !in_transaction;
+    if transaction {
+      client.begin().await?;
+    }
+    let mut result = StatementResult::default();
+    for chunk in rows.chunks(insert.chunk_rows(C::MAX_PARAMS)) {
+      let sql = insert.to_sql(chunk.len(), "");
+      let params = chunk
+        .iter()
+        .flat_map(|row| row.iter().cloned())
+        .collect::<Vec<_>>();
+      match client.execute(&sql, &params, options).await {
+        Ok(r) => result.extend(r),
+        Err(err) if transaction => {
+          if let Err(rollback_err) = client.rollback().await {
+            warn!(%err, %rollback_err, "rollback sql transaction failed");
+          }
+          return Err(err);
+        }
+        Err(err) => return Err(err),
+      }
+    }
+    if transaction {
+      client.commit().await?;
+    }
+    return Ok(result);
+  }
+
+  let mut result = StatementResult::default();
+  for row in rows {
+    let Value::Array(params) = row.as_ref() else {
+      anyhow::bail!("Unsupported bind parameters, each row should be an array");
+    };
+    result.extend(client.execute(&positional, params, options).await?);
+  }
+  Ok(result)
+}
+
+async fn client_schema<C: SqlClient>(client: &mut C) -> Result<Value> {
+  let options = DecodeOptions::default();
+  let queries = &C::SCHEMA;
+  let tables = client.execute(queries.tables, &[], &options).await?.rows;
+  let columns = client.execute(queries.columns, &[], &options).await?.rows;
+  let foreign_keys = match queries.foreign_keys {
+    "" => vec![],
+    sql => client.execute(sql, &[], &options).await?.rows,
+  };
+  Ok(json!({ "tables": schema_tables(tables, columns, foreign_keys) }))
+}
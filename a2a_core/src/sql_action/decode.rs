@@ -0,0 +1,156 @@
+use a2a_tojson::bytes_to_json;
+use a2a_types::{SqlAction, Value};
+use anyhow::Result;
+use serde_json::json;
+use sqlx::{ColumnIndex, Database, Decode, Row, ValueRef};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime, PrimitiveDateTime};
+use tracing::debug;
+
+/// how to convert the column values to json
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct DecodeOptions {
+  /// the datetime columns are ISO 8601 strings instead of epoch milliseconds
+  pub iso_dates: bool,
+}
+
+impl DecodeOptions {
+  pub(crate) fn from_action(action: &SqlAction) -> Result<Self> {
+    let iso_dates = match action.date_format.as_deref() {
+      None | Some("epoch") => false,
+      Some("iso") => true,
+      Some(other) => anyhow::bail!("Unsupported dateFormat `{}`, use `epoch` or `iso`", other),
+    };
+    Ok(Self { iso_dates })
+  }
+
+  pub(crate) fn datetime(&self, dt: OffsetDateTime) -> Value {
+    if self.iso_dates {
+      dt.format(&Rfc3339)
+        .map(Value::String)
+        .unwrap_or(Value::Null)
+    } else {
+      json!(dt.unix_timestamp_nanos() / 1_000_000)
+    }
+  }
+
+  /// the datetime without timezone is taken as utc
+  pub(crate) fn primitive_datetime(&self, dt: PrimitiveDateTime) -> Value {
+    self.datetime(dt.assume_utc())
+  }
+}
+
+pub(crate) fn is_null<R: Row>(row: &R, i: usize) -> bool
+where
+  usize: ColumnIndex<R>,
+{
+  row.try_get_raw(i).map(|v| v.is_null()).unwrap_or(true)
+}
+
+/// decode the column by the type matched by its type name, `None` if failed to decode
+pub(crate) fn get<'r, T, R>(row: &'r R, i: usize) -> Option<T>
+where
+  R: Row,
+  usize: ColumnIndex<R>,
+  T: Decode<'r, R::Database>,
+{
+  match row.try_get_unchecked::<T, _>(i) {
+    Ok(v) => Some(v),
+    Err(err) => {
+      debug!(%err, column = i, "decode sql column");
+      None
+    }
+  }
+}
+
+/// the binary column, parsed to json if it is a well-known format, or a data url
+pub(crate) fn bytes(data: Vec<u8>) -> Value {
+  bytes_to_json(bytes::Bytes::from(data), "", None).unwrap_or(Value::Null)
+}
+
+/// the decimal as string, so the precision is kept
+pub(crate) fn decimal(n: sqlx::types::BigDecimal) -> Value {
+  Value::String(n.normalized().to_plain_string())
+}
+
+/// the address without the prefix length when it is a single host, eg: `10.0.0.1`, `10.0.0.0/8`
+pub(crate) fn ip_net(net: sqlx::types::ipnet::IpNet) -> Value {
+  if net.prefix_len() == net.max_prefix_len() {
+    Value::String(net.addr().to_string())
+  } else {
+    Value::String(net.to_string())
+  }
+}
+
+/// the ISO 8601 duration, eg: `P1Y2M3DT4H5M6.5S`
+pub(crate) fn iso_duration(months: i32, days: i32, microseconds: i64) -> String {
+  let mut result = "P".to_string();
+  if months / 12 != 0 {
+    result.push_str(&format!("{}Y", months / 12));
+  }
+  if months % 12 != 0 {
+    result.push_str(&format!("{}M", months % 12));
+  }
+  if days != 0 {
+    result.push_str(&format!("{}D", days));
+  }
+  if microseconds != 0 || result == "P" {
+    result.push('T');
+    let hours = microseconds / 3_600_000_000;
+    let minutes = microseconds % 3_600_000_000 / 60_000_000;
+    let micros = microseconds % 60_000_000;
+    if hours != 0 {
+      result.push_str(&format!("{}H", hours));
+    }
+    if minutes != 0 {
+      result.push_str(&format!("{}M", minutes));
+    }
+    if micros != 0 || (hours == 0 && minutes == 0) {
+      let seconds = format!("{}.{:06}", micros / 1_000_000, (micros % 1_000_000).abs());
+      let seconds = seconds.trim_end_matches('0').trim_end_matches('.');
+      let sign = if micros < 0 && micros > -1_000_000 {
+        "-"
+      } else {
+        ""
+      };
+      result.push_str(&format!("{}{}S", sign, seconds));
+    }
+  }
+  result
+}
+
+/// decode the row to json object, the NULL columns are `null`,
+/// the unsupported columns are `null` too, cast them to text in the query
+pub(crate) fn row_to_object<R: Row>(
+  row: &R,
+  decode: impl Fn(&R, usize, &<R::Database as Database>::TypeInfo) -> Option<Value>,
+) -> Value
+where
+  usize: ColumnIndex<R>,
+{
+  let mut val = serde_json::Map::new();
+  for (i, col) in row.columns().iter().enumerate() {
+    let value = if is_null(row, i) {
+      Value::Null
+    } else {
+      decode(row, i, sqlx::Column::type_info(col)).unwrap_or(Value::Null)
+    };
+    val.insert(sqlx::Column::name(col).to_string(), value);
+  }
+  Value::Object(val)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_iso_duration() {
+    assert_eq!(iso_duration(0, 0, 0), "PT0S");
+    assert_eq!(
+      iso_duration(14, 3, 4 * 3_600_000_000 + 5 * 60_000_000 + 6_500_000),
+      "P1Y2M3DT4H5M6.5S"
+    );
+    assert_eq!(iso_duration(0, 1, 0), "P1D");
+    assert_eq!(iso_duration(0, 0, -500_000), "PT-0.5S");
+  }
+}
@@ -0,0 +1,174 @@
+use std::str::FromStr;
+
+use a2a_types::Value;
+use anyhow::anyhow;
+use sqlx::types::ipnet::IpNet;
+use time::{
+  format_description::well_known::Rfc3339, macros::format_description, Date, OffsetDateTime,
+  PrimitiveDateTime, Time, UtcOffset,
+};
+
+/// the bytes of a base64 data url, eg: `data:image/png;base64,iVBOR...`
+pub(crate) fn data_url_bytes(s: &str) -> Option<Vec<u8>> {
+  let (meta, data) = s.strip_prefix("data:")?.split_once(',')?;
+  if !meta.ends_with(";base64") {
+    return None;
+  }
+  base64_simd::STANDARD.decode_to_vec(data.as_bytes()).ok()
+}
+
+/// the parameter with the explicit type, for the databases which can't infer the parameter types,
+/// eg: `{"$datetime": "2024-01-02T03:04:05Z"}`, `{"$blob": "data:image/png;base64,iVBOR..."}`
+pub(crate) enum TypedParam<'a> {
+  /// the datetime string or epoch milliseconds
+  Datetime(&'a Value),
+  /// the data url or base64 string
+  Blob(&'a Value),
+}
+
+impl<'a> TypedParam<'a> {
+  /// the object with a single `$datetime` or `$blob` key, other values are bound by their json type
+  pub(crate) fn from_value(v: &'a Value) -> Option<Self> {
+    let (key, value) = v.as_object().filter(|o| o.len() == 1)?.iter().next()?;
+    match key.as_str() {
+      "$datetime" => Some(Self::Datetime(value)),
+      "$blob" => Some(Self::Blob(value)),
+      _ => None,
+    }
+  }
+
+  /// the value inside the type
+  pub(crate) fn value(&self) -> &'a Value {
+    match self {
+      Self::Datetime(v) | Self::Blob(v) => v,
+    }
+  }
+}
+
+/// the bytes of the data url or base64 string
+pub(crate) fn to_bytes(v: &Value) -> Option<Vec<u8>> {
+  let s = v.as_str()?;
+  data_url_bytes(s).or_else(|| base64_simd::STANDARD.decode_to_vec(s.as_bytes()).ok())
+}
+
+/// the ISO 8601 datetime with offset, eg: `2024-01-02T03:04:05Z`, `2024-01-02T03:04:05.5+08:00`
+pub(crate) fn parse_iso_datetime(s: &str) -> Option<OffsetDateTime> {
+  OffsetDateTime::parse(s, &Rfc3339).ok()
+}
+
+/// the datetime string or epoch milliseconds, the datetime without offset is taken as utc
+pub(crate) fn to_datetime(v: &Value) -> Option<OffsetDateTime> {
+  match v {
+    Value::Number(n) => {
+      let ms = n.as_i64()?;
+      OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000).ok()
+    }
+    Value::String(s) => parse_iso_datetime(s)
+      .or_else(|| {
+        let format = format_description!(
+          "[year]-[month]-[day][first [T] [ ]][hour]:[minute]:[second][optional [.[subsecond]]]"
+        );
+        PrimitiveDateTime::parse(s, format)
+          .ok()
+          .map(|dt| dt.assume_utc())
+      })
+      .or_else(|| to_date(v).map(|d| d.midnight().assume_utc())),
+    _ => None,
+  }
+}
+
+/// the datetime in utc without offset
+pub(crate) fn to_utc_datetime(v: &Value) -> Option<PrimitiveDateTime> {
+  let dt = to_datetime(v)?.to_offset(UtcOffset::UTC);
+  Some(PrimitiveDateTime::new(dt.date(), dt.time()))
+}
+
+pub(crate) fn to_date(v: &Value) -> Option<Date> {
+  let s = v.as_str()?;
+  let format = format_description!("[year]-[month]-[day]");
+  Date::parse(s.get(..10)?, format).ok()
+}
+
+pub(crate) fn to_time(v: &Value) -> Option<Time> {
+  let format =
+    format_description!("[hour]:[minute][optional [:[second][optional [.[subsecond]]]]]");
+  Time::parse(v.as_str()?, format).ok()
+}
+
+/// the integer, or the string of integer
+pub(crate) fn to_i64(v: &Value) -> Option<i64> {
+  match v {
+    Value::Number(n) => n.as_i64(),
+    Value::String(s) => s.trim().parse().ok(),
+    _ => None,
+  }
+}
+
+/// the number, or the string of number
+pub(crate) fn to_f64(v: &Value) -> Option<f64> {
+  match v {
+    Value::Number(n) => n.as_f64(),
+    Value::String(s) => s.trim().parse().ok(),
+    _ => None,
+  }
+}
+
+/// the network or the single host address, eg: `10.0.0.0/8`, `10.0.0.1`
+pub(crate) fn to_ip_net(v: &Value) -> Option<IpNet> {
+  let s = v.as_str()?;
+  IpNet::from_str(s)
+    .ok()
+    .or_else(|| std::net::IpAddr::from_str(s).ok().map(IpNet::from))
+}
+
+pub(crate) fn unsupported(v: &Value, type_name: &str) -> anyhow::Error {
+  anyhow!("Unsupported value `{}` for {} parameter", v, type_name)
+}
+
+pub(crate) fn bind_error(index: usize, err: impl std::fmt::Display) -> anyhow::Error {
+  anyhow!("Bind parameter {} failed: {}", index + 1, err)
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::json;
+
+  use super::*;
+
+  #[test]
+  fn test_to_datetime() {
+    let expected = 1704164645000i128 * 1_000_000;
+    for v in [
+      json!("2024-01-02T03:04:05Z"),
+      json!("2024-01-02T11:04:05+08:00"),
+      json!("2024-01-02 03:04:05"),
+      json!(1704164645000i64),
+    ] {
+      assert_eq!(
+        to_datetime(&v).map(|d| d.unix_timestamp_nanos()),
+        Some(expected),
+        "{}",
+        v
+      );
+    }
+    assert_eq!(to_date(&json!("2024-01-02T03:04:05Z")).unwrap().day(), 2);
+    assert!(to_datetime(&json!("yesterday")).is_none());
+    assert_eq!(
+      data_url_bytes("data:application/octet-stream;base64,AP8="),
+      Some(vec![0, 255])
+    );
+
+    let blob = json!({"$blob": "AP8="});
+    assert!(matches!(
+      TypedParam::from_value(&blob),
+      Some(TypedParam::Blob(_))
+    ));
+    assert_eq!(
+      to_bytes(TypedParam::from_value(&blob).unwrap().value()),
+      Some(vec![0, 255])
+    );
+    // the json objects are not typed
+    assert!(TypedParam::from_value(&json!({"$datetime": 1, "a": 2})).is_none());
+    assert!(TypedParam::from_value(&json!({"datetime": 1})).is_none());
+  }
+}
@@ -0,0 +1,319 @@
+use a2a_types::Value;
+use anyhow::{anyhow, Result};
+use futures::TryStreamExt;
+use opendal::Writer;
+use rust_xlsxwriter::Workbook;
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{Column, Executor, IntoArguments, Row};
+use tokio::io::AsyncReadExt;
+use tracing::{debug, warn};
+
+use super::{
+  decode::DecodeOptions,
+  runner::{bind_query, single_row_query, SqlDatabase},
+};
+use crate::{file_action::path_operator, utils::uuid_v7};
+
+/// the buffered bytes are written to the file when they exceed it
+const FLUSH_SIZE: usize = 4 * 1024 * 1024;
+
+/// the max rows of a worksheet including the header, the rows are continued in a new worksheet
+const XLSX_MAX_ROWS: u32 = 1_048_576;
+
+/// where to save the rows of the query, `SqlAction.saveTo`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SaveTo {
+  /// the file path with the scheme of the file action, eg: `s3://bucket/users.csv`
+  path: String,
+  /// csv, ndjson or xlsx, default by the file extension
+  format: Option<String>,
+  /// the connection of the file storage, same as `FileAction.connection`
+  connection: Option<Value>,
+}
+
+impl SaveTo {
+  pub(crate) fn from_value(value: &Value) -> Result<Self> {
+    match value {
+      Value::String(path) => Ok(Self {
+        path: path.clone(),
+        format: None,
+        connection: None,
+      }),
+      _ => Ok(serde_json::from_value(value.clone())?),
+    }
+  }
+
+  fn format(&self) -> Result<Format> {
+    let format = match self.format.as_deref() {
+      Some(format) => format.to_lowercase(),
+      None => self
+        .path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default(),
+    };
+    match format.as_str() {
+      "csv" => Ok(Format::Csv),
+      "ndjson" | "jsonl" => Ok(Format::Ndjson),
+      "xlsx" => Ok(Format::Xlsx),
+      _ => Err(anyhow!(
+        "Unsupported saveTo format `{}`, use csv, ndjson or xlsx",
+        format
+      )),
+    }
+  }
+}
+
+enum Format {
+  Csv,
+  Ndjson,
+  Xlsx,
+}
+
+/// stream the rows of the query to the file, the rows are not kept in memory,
+/// return the path and the count of rows, the partial file is removed when failed
+pub(crate) async fn save_to<DB>(
+  conn: &mut DB::Connection,
+  query: &str,
+  rows: Option<&Value>,
+  save: &SaveTo,
+  options: &DecodeOptions,
+) -> Result<Value>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let file = RowWriter::new(save.format()?);
+  let (op, path) = path_operator(&save.path, save.connection.as_ref())?;
+  let (sql, params) = single_row_query::<DB>(query, rows)?;
+  let mut writer = op.writer(&path).await?;
+
+  let written =
+    match write_rows::<DB>(conn, &sql, params.as_ref(), file, &mut writer, options).await {
+      Ok(count) => writer.close().await.map(|_| count).map_err(Into::into),
+      Err(err) => {
+        if let Err(err) = writer.abort().await {
+          debug!(path, %err, "abort saveTo writer");
+        }
+        Err(err)
+      }
+    };
+  if written.is_err() {
+    if let Err(err) = op.delete(&path).await {
+      warn!(path, %err, "remove the partial saveTo file");
+    }
+  }
+  Ok(json!({ "path": save.path, "rows": written? }))
+}
+
+async fn write_rows<DB>(
+  conn: &mut DB::Connection,
+  sql: &str,
+  params: Option<&Value>,
+  mut file: RowWriter,
+  writer: &mut Writer,
+  options: &DecodeOptions,
+) -> Result<u64>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let query = bind_query::<DB>(conn, sql, params).await?;
+  let mut stream = conn.fetch(query);
+  let mut columns = None;
+  let mut count = 0;
+  while let Some(row) = stream.try_next().await? {
+    // the columns in the order of the query, the keys of the json object are sorted
+    let columns = columns.get_or_insert_with(|| {
+      row
+        .columns()
+        .iter()
+        .map(|c| c.name().to_string())
+        .collect::<Vec<_>>()
+    });
+    if count == 0 {
+      file.write_header(columns)?;
+    }
+    file.write_row(columns, &DB::row_to_value(row, options))?;
+    count += 1;
+
+    if let Some(buffer) = file.take_buffer(FLUSH_SIZE)? {
+      writer.write(buffer).await?;
+    }
+  }
+  drop(stream);
+
+  // the header of the empty result is by the columns of the statement
+  if count == 0 {
+    let describe = conn.describe(sql).await?;
+    let columns = describe
+      .columns()
+      .iter()
+      .map(|c| c.name().to_string())
+      .collect::<Vec<_>>();
+    file.write_header(&columns)?;
+  }
+
+  file.finish(writer).await?;
+  Ok(count)
+}
+
+/// encode the rows to the file format, csv and ndjson are buffered and flushed by the caller,
+/// xlsx is kept in the temporary files until finished
+enum RowWriter {
+  Csv(Box<csv::Writer<Vec<u8>>>),
+  Ndjson(Vec<u8>),
+  Xlsx { workbook: Box<Workbook>, row: u32 },
+}
+
+impl RowWriter {
+  fn new(format: Format) -> Self {
+    match format {
+      Format::Csv => Self::Csv(Box::new(csv::Writer::from_writer(Vec::new()))),
+      Format::Ndjson => Self::Ndjson(Vec::new()),
+      Format::Xlsx => {
+        let mut workbook = Box::new(Workbook::new());
+        workbook.add_worksheet_with_constant_memory();
+        Self::Xlsx { workbook, row: 0 }
+      }
+    }
+  }
+
+  fn write_header(&mut self, columns: &[String]) -> Result<()> {
+    match self {
+      Self::Csv(w) => w.write_record(columns)?,
+      Self::Ndjson(_) => {}
+      Self::Xlsx { .. } => {
+        let header = columns.iter().map(|c| Value::String(c.clone()));
+        self.write_xlsx_row(&header.collect::<Vec<_>>())?
+      }
+    }
+    Ok(())
+  }
+
+  fn write_row(&mut self, columns: &[String], row: &Value) -> Result<()> {
+    match self {
+      Self::Csv(w) => w.write_record(columns.iter().map(|c| csv_field(&row[c.as_str()])))?,
+      Self::Ndjson(buffer) => {
+        // keep the order of the columns, the keys of the json object are sorted
+        buffer.push(b'{');
+        for (i, c) in columns.iter().enumerate() {
+          if i > 0 {
+            buffer.push(b',');
+          }
+          serde_json::to_writer(&mut *buffer, c)?;
+          buffer.push(b':');
+          serde_json::to_writer(&mut *buffer, &row[c.as_str()])?;
+        }
+        buffer.extend_from_slice(b"}\n");
+      }
+      Self::Xlsx { .. } => {
+        let values = columns.iter().map(|c| row[c.as_str()].clone());
+        self.write_xlsx_row(&values.collect::<Vec<_>>())?
+      }
+    }
+    Ok(())
+  }
+
+  /// write the row to the last worksheet, a new worksheet is added when it is full
+  fn write_xlsx_row(&mut self, values: &[Value]) -> Result<()> {
+    let Self::Xlsx { workbook, row } = self else {
+      return Ok(());
+    };
+    if *row == XLSX_MAX_ROWS {
+      workbook.add_worksheet_with_constant_memory();
+      *row = 0;
+    }
+    let index = workbook.worksheets().len() - 1;
+    let sheet = workbook.worksheet_from_index(index)?;
+    for (col, value) in values.iter().enumerate() {
+      let col = col as u16;
+      match value {
+        Value::Null => {}
+        Value::Bool(b) => {
+          sheet.write_boolean(*row, col, *b)?;
+        }
+        Value::Number(n) => {
+          sheet.write_number(*row, col, n.as_f64().unwrap_or_default())?;
+        }
+        Value::String(s) => {
+          sheet.write_string(*row, col, s)?;
+        }
+        _ => {
+          sheet.write_string(*row, col, value.to_string())?;
+        }
+      }
+    }
+    *row += 1;
+    Ok(())
+  }
+
+  /// the buffered bytes when they exceed the size
+  fn take_buffer(&mut self, size: usize) -> Result<Option<Vec<u8>>> {
+    match self {
+      Self::Csv(w) => {
+        w.flush()?;
+        if w.get_ref().len() < size {
+          return Ok(None);
+        }
+        let w = std::mem::replace(w, Box::new(csv::Writer::from_writer(Vec::new())));
+        Ok(Some(
+          w.into_inner().map_err(|err| anyhow!("{}", err.error()))?,
+        ))
+      }
+      Self::Ndjson(buffer) => Ok((buffer.len() >= size).then(|| std::mem::take(buffer))),
+      Self::Xlsx { .. } => Ok(None),
+    }
+  }
+
+  /// write the remaining bytes of the file, the xlsx is saved to a temporary file first,
+  /// so the zip of the workbook is not built in memory
+  async fn finish(self, writer: &mut Writer) -> Result<()> {
+    match self {
+      Self::Csv(w) => {
+        writer
+          .write(w.into_inner().map_err(|err| anyhow!("{}", err.error()))?)
+          .await?
+      }
+      Self::Ndjson(buffer) => writer.write(buffer).await?,
+      Self::Xlsx { mut workbook, .. } => {
+        let temp = std::env::temp_dir().join(format!("a2a_{}.xlsx", uuid_v7()));
+        // the zip of a large workbook takes a while, not on the async runtime
+        let path = temp.clone();
+        let copied = match tokio::task::spawn_blocking(move || workbook.save(&path)).await? {
+          Ok(_) => copy_file(&temp, writer).await,
+          Err(err) => Err(err.into()),
+        };
+        tokio::fs::remove_file(&temp).await.ok();
+        copied?
+      }
+    }
+    Ok(())
+  }
+}
+
+/// write the local file to the writer by chunks
+async fn copy_file(path: &std::path::Path, writer: &mut Writer) -> Result<()> {
+  let mut file = tokio::fs::File::open(path).await?;
+  let mut buffer = vec![0; FLUSH_SIZE];
+  loop {
+    let n = file.read(&mut buffer).await?;
+    if n == 0 {
+      return Ok(());
+    }
+    writer.write(buffer[..n].to_vec()).await?;
+  }
+}
+
+/// the string as is, null is empty, the others are json
+fn csv_field(value: &Value) -> String {
+  match value {
+    Value::Null => String::new(),
+    Value::String(s) => s.clone(),
+    _ => value.to_string(),
+  }
+}
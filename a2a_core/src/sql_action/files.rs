@@ -0,0 +1,205 @@
+use std::path::{Component, Path, PathBuf};
+
+use a2a_tojson::bytes_to_json;
+use a2a_types::{SqlAction, SqlActionResult, Value};
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use sqlx::Sqlite;
+use tracing::debug;
+
+use super::{
+  lexer::{tokenize, Token},
+  pool::sqlite_pool,
+  runner::run_action,
+};
+use crate::file_action::mimetype_from_ext;
+
+/// the extensions of the files can be queried as tables, in the order to find a table by name
+const TABLE_EXTENSIONS: &[&str] = &["csv", "xlsx", "xls", "json", "ndjson", "jsonl"];
+
+/// run the sql over the files, the connection is `files://<dir>`, default is the current dir.
+///
+/// the tables referenced by the query are loaded from the files in the dir into a memory sqlite database,
+/// eg: `SELECT * FROM sales` loads `sales.csv`, `SELECT * FROM "2024/sales.xlsx"` loads the file by its path
+pub(crate) async fn do_sql_action(action: SqlAction) -> Result<SqlActionResult> {
+  let dir = match action.connection.as_str() {
+    "" => PathBuf::from("."),
+    dir => PathBuf::from(dir),
+  };
+
+  let mut names = table_names(&action.query);
+  for statement in action.statements.iter().flatten() {
+    names.extend(table_names(&statement.query));
+  }
+  names.sort();
+  names.dedup();
+
+  let mut conn = sqlite_pool(":memory:")?.acquire().await?;
+  for name in names {
+    let Some(file) = table_file(&dir, &name) else {
+      continue;
+    };
+    debug!(table = %name, file = %file.display(), "load sql table from file");
+    let (columns, rows) = read_table(&file)
+      .await
+      .map_err(|err| anyhow!("Load table `{}` failed: {}", name, err))?;
+    load_table(&mut conn, &name, &columns, rows).await?;
+  }
+
+  run_action::<Sqlite>(&mut conn, &action).await
+}
+
+/// the identifiers in the query, they are the tables if the files exist
+fn table_names(sql: &str) -> Vec<String> {
+  tokenize(sql, false)
+    .into_iter()
+    .filter_map(|t| match t {
+      Token::Word(w) => Some(w),
+      Token::QuotedIdent(q) if q.starts_with('"') => Some(q[1..q.len() - 1].replace("\"\"", "\"")),
+      _ => None,
+    })
+    .collect()
+}
+
+/// the file of the table, the path must be relative and inside the dir,
+/// the name without extension is found by the supported extensions
+fn table_file(dir: &Path, name: &str) -> Option<PathBuf> {
+  let path = Path::new(name);
+  if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+    return None;
+  }
+  let ext = path
+    .extension()
+    .and_then(|e| e.to_str())
+    .unwrap_or_default();
+  if TABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
+    let file = dir.join(path);
+    return file.is_file().then_some(file);
+  }
+  TABLE_EXTENSIONS
+    .iter()
+    .map(|ext| dir.join(format!("{}.{}", name, ext)))
+    .find(|file| file.is_file())
+}
+
+/// the columns and rows of the file, parsed by its extension
+async fn read_table(file: &Path) -> Result<(Vec<String>, Vec<Value>)> {
+  let body = tokio::fs::read(file).await?;
+  let mimetype = mimetype_from_ext(&file.to_string_lossy());
+  // the csv and excel rows are arrays with the header row, so the columns keep their order,
+  // the table of the excel is its first sheet
+  let options = json!({"has_header": true, "as_object": false, "sheet": 0});
+  let value = bytes_to_json(body.into(), &mimetype, Some(&options))?;
+  let rows = match value {
+    Value::Array(rows) => rows,
+    Value::Null => vec![],
+    value => vec![value],
+  };
+
+  if rows.iter().all(|r| r.is_array()) {
+    let mut rows = rows.into_iter();
+    let header = match rows.next() {
+      Some(Value::Array(header)) => header,
+      _ => vec![],
+    };
+    let columns = column_names(header.iter().map(|h| match h {
+      Value::String(s) => s.clone(),
+      Value::Null => String::new(),
+      h => h.to_string(),
+    }));
+    let rows = rows
+      .map(|row| match row {
+        Value::Array(mut a) => {
+          a.resize(columns.len(), Value::Null);
+          // the empty fields are NULL
+          a.iter_mut()
+            .filter(|v| v.as_str() == Some(""))
+            .for_each(|v| *v = Value::Null);
+          Value::Array(a)
+        }
+        row => row,
+      })
+      .collect();
+    return Ok((columns, rows));
+  }
+
+  if rows.iter().all(|r| r.is_object()) {
+    // the keys of all rows in the order they appear
+    let mut keys: Vec<&String> = vec![];
+    for row in rows.iter().filter_map(|r| r.as_object()) {
+      for key in row.keys() {
+        if !keys.contains(&key) {
+          keys.push(key);
+        }
+      }
+    }
+    let keys = keys.into_iter().cloned().collect::<Vec<_>>();
+    let rows = rows
+      .iter()
+      .map(|row| Value::Array(keys.iter().map(|k| row[k.as_str()].clone()).collect()))
+      .collect();
+    return Ok((column_names(keys.into_iter()), rows));
+  }
+
+  // the scalar values, eg: `[1, 2, 3]`
+  let rows = rows.into_iter().map(|v| json!([v])).collect();
+  Ok((vec!["value".to_string()], rows))
+}
+
+/// the empty names are `column1`, `column2`..., the duplicated names are suffixed, eg: `name_2`
+fn column_names(names: impl Iterator<Item = String>) -> Vec<String> {
+  let mut columns: Vec<String> = vec![];
+  for (i, name) in names.enumerate() {
+    let name = match name.trim() {
+      "" => format!("column{}", i + 1),
+      name => name.to_string(),
+    };
+    let mut unique = name.clone();
+    let mut n = 1;
+    while columns.iter().any(|c| c.eq_ignore_ascii_case(&unique)) {
+      n += 1;
+      unique = format!("{}_{}", name, n);
+    }
+    columns.push(unique);
+  }
+  columns
+}
+
+/// create the table without column types, sqlite keeps the type of each value
+async fn load_table(
+  conn: &mut <Sqlite as sqlx::Database>::Connection,
+  name: &str,
+  columns: &[String],
+  rows: Vec<Value>,
+) -> Result<()> {
+  if columns.is_empty() {
+    anyhow::bail!("Load table `{}` failed: no columns", name);
+  }
+  let quoted = columns.iter().map(|c| quote(c)).collect::<Vec<_>>();
+  let create = SqlAction {
+    query: format!("CREATE TABLE {} ({})", quote(name), quoted.join(", ")),
+    ..Default::default()
+  };
+  run_action::<Sqlite>(conn, &create).await?;
+  if rows.is_empty() {
+    return Ok(());
+  }
+
+  let insert = SqlAction {
+    query: format!(
+      "INSERT INTO {} ({}) VALUES ({})",
+      quote(name),
+      quoted.join(", "),
+      vec!["?"; columns.len()].join(", ")
+    ),
+    rows: Some(Value::Array(rows)),
+    transaction: Some(true),
+    ..Default::default()
+  };
+  run_action::<Sqlite>(conn, &insert).await?;
+  Ok(())
+}
+
+fn quote(name: &str) -> String {
+  format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,269 @@
+/// sql token, with the original text so the sql can be rebuilt by joining the tokens
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+  Space(String),
+  /// `-- ...` or `/* ... */`
+  Comment(String),
+  /// `'...'`, `E'...'` or postgres dollar quoted `$tag$...$tag$`
+  Str(String),
+  /// `"..."` or `` `...` ``
+  QuotedIdent(String),
+  Word(String),
+  Number(String),
+  /// positional placeholder `?`
+  Param,
+  /// named placeholder `:name` or `@name`
+  Named(String),
+  /// operators and punctuations, eg: `(`, `::`, `?|`, `$1`
+  Other(String),
+}
+
+impl Token {
+  pub(crate) fn text(&self) -> &str {
+    match self {
+      Token::Space(s)
+      | Token::Comment(s)
+      | Token::Str(s)
+      | Token::QuotedIdent(s)
+      | Token::Word(s)
+      | Token::Number(s)
+      | Token::Named(s)
+      | Token::Other(s) => s,
+      Token::Param => "?",
+    }
+  }
+
+  /// not space or comment
+  pub(crate) fn is_significant(&self) -> bool {
+    !matches!(self, Token::Space(_) | Token::Comment(_))
+  }
+
+  pub(crate) fn is_word(&self, word: &str) -> bool {
+    matches!(self, Token::Word(w) if w.eq_ignore_ascii_case(word))
+  }
+}
+
+/// split the sql to tokens, `backslash_escapes` is true for mysql, whose strings escape quote by `\'`
+pub(crate) fn tokenize(sql: &str, backslash_escapes: bool) -> Vec<Token> {
+  let chars = sql.chars().collect::<Vec<_>>();
+  let mut tokens = Vec::new();
+  let mut i = 0;
+  while i < chars.len() {
+    let c = chars[i];
+    let next = chars.get(i + 1).copied();
+    let (token, end) = match c {
+      c if c.is_whitespace() => {
+        let end = scan_while(&chars, i, |c| c.is_whitespace());
+        (Token::Space(collect(&chars, i, end)), end)
+      }
+      '-' if next == Some('-') => {
+        let end = scan_while(&chars, i, |c| c != '\n');
+        (Token::Comment(collect(&chars, i, end)), end)
+      }
+      '/' if next == Some('*') => {
+        let end = find_str(&chars, i + 2, &['*', '/']);
+        (Token::Comment(collect(&chars, i, end)), end)
+      }
+      '\'' => {
+        let end = scan_quoted(&chars, i, '\'', backslash_escapes);
+        (Token::Str(collect(&chars, i, end)), end)
+      }
+      'E' | 'e' if next == Some('\'') && !prev_is_ident(&chars, i) => {
+        let end = scan_quoted(&chars, i + 1, '\'', true);
+        (Token::Str(collect(&chars, i, end)), end)
+      }
+      '"' | '`' => {
+        let end = scan_quoted(&chars, i, c, false);
+        (Token::QuotedIdent(collect(&chars, i, end)), end)
+      }
+      '$' => match dollar_tag(&chars, i) {
+        Some(tag) => {
+          let end = find_str(&chars, i + tag.len(), &tag);
+          (Token::Str(collect(&chars, i, end)), end)
+        }
+        None => {
+          // positional parameter of postgres, eg: `$1`
+          let end = scan_while(&chars, i + 1, |c| c.is_ascii_digit());
+          (Token::Other(collect(&chars, i, end)), end)
+        }
+      },
+      '?' => match next {
+        Some('|') | Some('&') | Some('?') => (Token::Other(collect(&chars, i, i + 2)), i + 2),
+        _ => (Token::Param, i + 1),
+      },
+      ':' | '@' if next == Some(c) => (Token::Other(collect(&chars, i, i + 2)), i + 2),
+      ':' if next == Some('=') => (Token::Other(collect(&chars, i, i + 2)), i + 2),
+      ':' | '@' if next.is_some_and(is_ident_start) && !prev_is_ident(&chars, i) => {
+        let end = scan_while(&chars, i + 1, is_ident_char);
+        (Token::Named(collect(&chars, i, end)), end)
+      }
+      c if is_ident_start(c) => {
+        let end = scan_while(&chars, i, |c| is_ident_char(c) || c == '$');
+        (Token::Word(collect(&chars, i, end)), end)
+      }
+      c if c.is_ascii_digit() => {
+        let end = scan_while(&chars, i, |c| c.is_ascii_alphanumeric() || c == '.');
+        (Token::Number(collect(&chars, i, end)), end)
+      }
+      _ => (Token::Other(c.to_string()), i + 1),
+    };
+    tokens.push(token);
+    i = end;
+  }
+  tokens
+}
+
+/// the keywords of the statements which return rows
+const QUERY_KEYWORDS: &[&str] = &[
+  "SELECT", "VALUES", "TABLE", "SHOW", "DESCRIBE", "DESC", "EXPLAIN", "PRAGMA", "CALL",
+];
+
+/// the keywords of the statements which write rows
+const WRITE_KEYWORDS: &[&str] = &["INSERT", "UPDATE", "DELETE", "REPLACE", "MERGE"];
+
+/// the kind of the sql statement in upper case, eg: `SELECT`, `INSERT`,
+/// the statement after the `WITH` clause is used, eg: `WITH a AS (...) INSERT ...` is `INSERT`
+pub(crate) fn statement_kind(sql: &str) -> Option<String> {
+  let tokens = tokenize(sql, false);
+  let mut words = tokens.iter().filter(|t| t.is_significant());
+  let first = match words.next()? {
+    Token::Word(w) => w.to_uppercase(),
+    _ => return None,
+  };
+  if first != "WITH" {
+    return Some(first);
+  }
+  let mut depth = 0;
+  for token in words {
+    match token {
+      Token::Other(s) if s == "(" => depth += 1,
+      Token::Other(s) if s == ")" => depth -= 1,
+      Token::Word(w) if depth == 0 => {
+        let w = w.to_uppercase();
+        if QUERY_KEYWORDS.contains(&w.as_str()) || WRITE_KEYWORDS.contains(&w.as_str()) {
+          return Some(w);
+        }
+      }
+      _ => {}
+    }
+  }
+  Some(first)
+}
+
+/// the statement returns rows, a query like `SELECT`, or a write with `RETURNING`
+pub(crate) fn returns_rows(sql: &str) -> bool {
+  match statement_kind(sql) {
+    Some(kind) if QUERY_KEYWORDS.contains(&kind.as_str()) => true,
+    Some(_) => tokenize(sql, false).iter().any(|t| t.is_word("RETURNING")),
+    None => false,
+  }
+}
+
+fn collect(chars: &[char], start: usize, end: usize) -> String {
+  chars[start..end.min(chars.len())].iter().collect()
+}
+
+fn scan_while(chars: &[char], from: usize, f: impl Fn(char) -> bool) -> usize {
+  chars[from..]
+    .iter()
+    .position(|c| !f(*c))
+    .map(|p| from + p)
+    .unwrap_or(chars.len())
+}
+
+/// the index after the closing quote, a doubled quote is an escaped quote
+fn scan_quoted(chars: &[char], start: usize, quote: char, backslash_escapes: bool) -> usize {
+  let mut i = start + 1;
+  while i < chars.len() {
+    match chars[i] {
+      '\\' if backslash_escapes => i += 2,
+      c if c == quote => {
+        if chars.get(i + 1) == Some(&quote) {
+          i += 2;
+        } else {
+          return i + 1;
+        }
+      }
+      _ => i += 1,
+    }
+  }
+  chars.len()
+}
+
+/// the index after the closing chars, or the end of the sql
+fn find_str(chars: &[char], from: usize, close: &[char]) -> usize {
+  if from >= chars.len() {
+    return chars.len();
+  }
+  chars[from..]
+    .windows(close.len())
+    .position(|w| w == close)
+    .map(|p| from + p + close.len())
+    .unwrap_or(chars.len())
+}
+
+/// the tag of postgres dollar quoted string, eg: `$$` or `$body$`
+fn dollar_tag(chars: &[char], start: usize) -> Option<Vec<char>> {
+  if chars.get(start + 1).is_some_and(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  let end = scan_while(chars, start + 1, is_ident_char);
+  (chars.get(end) == Some(&'$')).then(|| chars[start..=end].to_vec())
+}
+
+fn prev_is_ident(chars: &[char], i: usize) -> bool {
+  i > 0 && is_ident_char(chars[i - 1])
+}
+
+fn is_ident_start(c: char) -> bool {
+  c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+  c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_tokenize() {
+    let sql = "SELECT a::int, '?'' :x', E'\\'?', $$ ? $$, \"?\" FROM t -- ?\nWHERE j ?| b AND id = ? /* :y */ AND n = :name";
+    let tokens = tokenize(sql, false);
+    assert_eq!(tokens.iter().filter(|t| **t == Token::Param).count(), 1);
+    assert_eq!(
+      tokens
+        .iter()
+        .filter_map(|t| match t {
+          Token::Named(n) => Some(&n[1..]),
+          _ => None,
+        })
+        .collect::<Vec<_>>(),
+      vec!["name"]
+    );
+    assert!(tokens.contains(&Token::Other("?|".to_string())));
+    assert!(tokens.contains(&Token::Str("$$ ? $$".to_string())));
+
+    // mysql escapes quote by backslash
+    let tokens = tokenize(r"SELECT 'it\'s ?', ?", true);
+    assert_eq!(tokens.iter().filter(|t| **t == Token::Param).count(), 1);
+  }
+
+  #[test]
+  fn test_statement_kind() {
+    assert_eq!(statement_kind(" -- x\nselect 1").as_deref(), Some("SELECT"));
+    assert_eq!(
+      statement_kind("WITH a AS (SELECT 1) INSERT INTO t SELECT * FROM a").as_deref(),
+      Some("INSERT")
+    );
+    assert_eq!(
+      statement_kind("CREATE TABLE t (id INT)").as_deref(),
+      Some("CREATE")
+    );
+    assert!(returns_rows("SELECT * FROM t FOR UPDATE"));
+    assert!(returns_rows("INSERT INTO t VALUES (1) RETURNING id"));
+    assert!(!returns_rows("UPDATE t SET note = 'RETURNING'"));
+    assert!(!returns_rows("DELETE FROM t"));
+  }
+}
@@ -1,8 +1,22 @@
 use a2a_types::{SqlAction, SqlActionResult, Value};
 use anyhow::Result;
 
+mod bulk;
+mod clickhouse;
+mod client;
+mod decode;
+mod encode;
+mod export;
+mod files;
+mod lexer;
+mod mssql;
 mod mysql;
+mod params;
 mod pgsql;
+mod policy;
+mod pool;
+mod runner;
+mod schema;
 mod sqlite;
 
 fn sql_driver(conn: &str) -> (&'static str, String) {
@@ -11,6 +25,9 @@ fn sql_driver(conn: &str) -> (&'static str, String) {
       "mysql" | "my" => ("mysql", format!("mysql://{}", conn)),
       "postgres" | "pgsql" | "pg" | "postgresql" => ("postgres", format!("postgres://{}", conn)),
       "sqlite" => ("sqlite", format!("{}", conn)),
+      "mssql" | "sqlserver" => ("mssql", format!("mssql://{}", conn)),
+      "clickhouse" | "ch" => ("clickhouse", format!("clickhouse://{}", conn)),
+      "files" => ("files", conn.to_string()),
       _ => ("", "".to_string()),
     }
   } else {
@@ -19,15 +36,22 @@ fn sql_driver(conn: &str) -> (&'static str, String) {
 }
 
 pub async fn do_action(mut action: SqlAction) -> Result<SqlActionResult> {
-  let (schema, conn) = sql_driver(&action.connection);
+  let (policy, conn) = policy::SqlPolicy::from_connection(&action.connection)?;
+  let (schema, conn) = sql_driver(&conn);
 
   // normalize connection string
   action.connection = conn;
 
+  // the statements are checked before connecting, mysql and clickhouse strings escape quote by backslash
+  policy.check_action(&action, matches!(schema, "mysql" | "clickhouse"))?;
+
   match schema {
     "mysql" => mysql::do_sql_action(action).await,
-    "postgres" => pgsql::do_sql_action(action).await,
+    "postgres" => pgsql::do_sql_action(action, policy.read_only).await,
     "sqlite" => sqlite::do_sql_action(action).await,
+    "mssql" => mssql::do_sql_action(action).await,
+    "clickhouse" => clickhouse::do_sql_action(action).await,
+    "files" => files::do_sql_action(action).await,
     _ => anyhow::bail!("Unsupported database driver"),
   }
 }
@@ -1,150 +1,242 @@
-use a2a_tojson::bytes_to_json;
 use a2a_types::{SqlAction, SqlActionResult, Value};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
 use serde_json::json;
 use sqlx::{
-  mysql::{MySqlArguments, MySqlRow},
-  Arguments, Column, Connection, MySqlConnection, Row, TypeInfo,
+  mysql::{
+    types::MySqlTime, MySqlArguments, MySqlConnection, MySqlQueryResult, MySqlRow, MySqlTypeInfo,
+  },
+  types::Json,
+  Arguments, MySql, TypeInfo,
 };
+use time::Date;
+use tracing::debug;
 
-use super::array_dim;
+use super::{
+  decode::{bytes, decimal, get, row_to_object, DecodeOptions},
+  encode::{bind_error, to_bytes, to_utc_datetime, unsupported, TypedParam},
+  pool::mysql_pool,
+  runner::{query_rows, run_action, SqlDatabase},
+  schema::SchemaQueries,
+};
 
 pub(crate) async fn do_sql_action(action: SqlAction) -> Result<SqlActionResult> {
-  let mut conn = MySqlConnection::connect(&action.connection).await?;
-  let sql = &action.query;
-  match array_dim(action.rows.as_ref()) {
-    0 => {
-      // no bind parameters
-      let rows = sqlx::query(sql).fetch_all(&mut conn).await?;
-      let mut result = Vec::new();
-      for row in rows {
-        result.push(row_to_value(row));
-      }
-      Ok(SqlActionResult::Array(result))
+  let mut conn = mysql_pool(&action.connection)?.acquire().await?;
+  run_action::<MySql>(&mut conn, &action).await
+}
+
+impl SqlDatabase for MySql {
+  const BACKSLASH_ESCAPES: bool = true;
+
+  const MAX_PARAMS: usize = u16::MAX as usize;
+
+  const SCHEMA: SchemaQueries = SchemaQueries {
+    tables: SCHEMA_TABLES,
+    columns: SCHEMA_COLUMNS,
+    foreign_keys: SCHEMA_FOREIGN_KEYS,
+    row_estimates: "",
+  };
+
+  fn row_to_value(row: MySqlRow, options: &DecodeOptions) -> Value {
+    row_to_value(row, options)
+  }
+
+  fn value_to_args(values: &[Value], _types: Option<&[MySqlTypeInfo]>) -> Result<MySqlArguments> {
+    value_to_args(values)
+  }
+
+  fn rows_affected(result: &MySqlQueryResult) -> u64 {
+    result.rows_affected()
+  }
+
+  fn last_insert_id(result: &MySqlQueryResult) -> Option<i64> {
+    // 0 when the statement doesn't generate an id
+    Some(result.last_insert_id() as i64).filter(|id| *id > 0)
+  }
+
+  /// the row alias `AS new` is used by mysql 8.0.19+, the deprecated `VALUES(col)` by older
+  /// versions and mariadb, which doesn't support the row alias
+  fn upsert_clause<'c>(
+    conn: &'c mut MySqlConnection,
+    keys: &[&str],
+    update: &[&str],
+  ) -> BoxFuture<'c, Result<String>> {
+    let keys = keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+    let update = update.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    Box::pin(async move {
+      let rows = query_rows::<MySql>(conn, "SELECT VERSION() AS version").await?;
+      let row_alias = rows
+        .first()
+        .and_then(|r| r["version"].as_str())
+        .is_some_and(supports_row_alias);
+      Ok(upsert_clause(&keys, &update, row_alias))
+    })
+  }
+}
+
+/// the row alias of the insert is supported since mysql 8.0.19, eg: `8.0.35`, not `10.11.6-MariaDB`
+fn supports_row_alias(version: &str) -> bool {
+  if version.to_lowercase().contains("mariadb") {
+    return false;
+  }
+  let mut numbers = version
+    .split(|c: char| !c.is_ascii_digit())
+    .map(|n| n.parse::<u32>().unwrap_or_default());
+  let version = (
+    numbers.next().unwrap_or_default(),
+    numbers.next().unwrap_or_default(),
+    numbers.next().unwrap_or_default(),
+  );
+  version >= (8, 0, 19)
+}
+
+fn upsert_clause(keys: &[String], update: &[String], row_alias: bool) -> String {
+  let value = |c: &str| match row_alias {
+    true => format!("new.{}", c),
+    false => format!("VALUES({})", c),
+  };
+  let set = if update.is_empty() {
+    // ignore the conflict by a no-op update
+    keys
+      .iter()
+      .take(1)
+      .map(|k| format!("{} = {}", k, k))
+      .collect::<Vec<_>>()
+  } else {
+    update
+      .iter()
+      .map(|c| format!("{} = {}", c, value(c)))
+      .collect()
+  };
+  let alias = if row_alias { " AS new" } else { "" };
+  format!("{} ON DUPLICATE KEY UPDATE {}", alias, set.join(", "))
+}
+
+fn row_to_value(row: MySqlRow, options: &DecodeOptions) -> Value {
+  row_to_object(&row, |row, i, type_info| decode(row, i, type_info, options))
+}
+
+fn decode(
+  row: &MySqlRow,
+  i: usize,
+  type_info: &MySqlTypeInfo,
+  options: &DecodeOptions,
+) -> Option<Value> {
+  let value = match type_info.name() {
+    "BOOLEAN" => json!(get::<bool, _>(row, i)?),
+    "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" | "ENUM" | "SET" => {
+      json!(get::<String, _>(row, i)?)
     }
-    1 => {
-      if let Some(args) = value_to_args(action.rows.as_ref()) {
-        let rows = sqlx::query_with(sql, args).fetch_all(&mut conn).await?;
-        let mut result = Vec::new();
-        for row in rows {
-          result.push(row_to_value(row));
-        }
-        Ok(SqlActionResult::Array(result))
-      } else {
-        anyhow::bail!("Unsupported bind parameters");
-      }
+    "TINYINT" => json!(get::<i8, _>(row, i)?),
+    "TINYINT UNSIGNED" => json!(get::<u8, _>(row, i)?),
+    "SMALLINT" => json!(get::<i16, _>(row, i)?),
+    "SMALLINT UNSIGNED" | "YEAR" => json!(get::<u16, _>(row, i)?),
+    "INT" | "MEDIUMINT" => json!(get::<i32, _>(row, i)?),
+    "INT UNSIGNED" | "MEDIUMINT UNSIGNED" => json!(get::<u32, _>(row, i)?),
+    "BIGINT" => json!(get::<i64, _>(row, i)?),
+    "BIGINT UNSIGNED" | "BIT" => json!(get::<u64, _>(row, i)?),
+    "FLOAT" => json!(get::<f32, _>(row, i)?),
+    "DOUBLE" => json!(get::<f64, _>(row, i)?),
+    "DECIMAL" => decimal(get(row, i)?),
+    "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "GEOMETRY" => {
+      bytes(get(row, i)?)
     }
-    2 => {
-      if let Some(Value::Array(a)) = action.rows {
-        for row in a {
-          if let Some(args) = value_to_args(Some(&row)) {
-            sqlx::query_with(sql, args).execute(&mut conn).await?;
-          }
-        }
-      }
-      Ok(SqlActionResult::Array(Vec::new()))
+    "JSON" => get::<Value, _>(row, i)?,
+    "DATETIME" => options.primitive_datetime(get(row, i)?),
+    "TIMESTAMP" => options.datetime(get(row, i)?),
+    "DATE" => json!(get::<Date, _>(row, i)?.to_string()),
+    // the mysql time is a duration, it can be negative or more than 24 hours
+    "TIME" => json!(get::<MySqlTime, _>(row, i)?.to_string()),
+    "NULL" => Value::Null,
+    name => {
+      debug!(type_name = name, "unsupported mysql type");
+      Value::Null
     }
-    _ => anyhow::bail!("Unsupported rows value"),
-  }
+  };
+  Some(value)
 }
 
-fn row_to_value(row: MySqlRow) -> Value {
-  let mut val = serde_json::Map::new();
-  row.columns().iter().enumerate().for_each(|(i, col)| {
-    let value = match col.type_info().name() {
-      "BOOL" | "BOOLEAN" => Value::Bool(row.get(i)),
-      "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" => Value::String(row.get(i)),
-      "TINYINT" => {
-        let n: i8 = row.get(i);
-        json!(n)
-      }
-      "TINYINT UNSIGNED" => {
-        let n: u8 = row.get(i);
-        json!(n)
-      }
-      "SMALLINT" => {
-        let n: i16 = row.get(i);
-        json!(n)
-      }
-      "SMALLINT UNSIGNED" => {
-        let n: u16 = row.get(i);
-        json!(n)
-      }
-      "INT" => {
-        let n: i32 = row.get(i);
-        json!(n)
-      }
-      "INT UNSIGNED" => {
-        let n: u32 = row.get(i);
-        json!(n)
-      }
-      "BIGINT" => {
-        let n: i64 = row.get(i);
-        json!(n)
-      }
-      "BIGINT UNSIGNED" => {
-        let n: u64 = row.get(i);
-        json!(n)
-      }
-      "FLOAT" => {
-        let n: f32 = row.get(i);
-        json!(n)
-      }
-      "DOUBLE" => {
-        let n: f64 = row.get(i);
-        json!(n)
-      }
-      "VARBINARY" | "BINARY" | "BLOB" => {
-        let data: Vec<u8> = row.get(i);
-        bytes_to_json(bytes::Bytes::from(data), "", None).unwrap_or(Value::Null)
-      }
-      "JSON" => {
-        let data: serde_json::Value = row.get(i);
-        data
-      }
-      "DATETIME" => {
-        let data: time::PrimitiveDateTime = row.get(i);
-        json!(data.assume_utc().unix_timestamp_nanos() / 1_000_000)
-      }
-      "TIMESTAMP" => {
-        let data: time::OffsetDateTime = row.get(i);
-        json!(data.unix_timestamp_nanos() / 1_000_000)
-      }
-      "DATE" => {
-        let data: time::Date = row.get(i);
-        json!(data.to_string())
-      }
-      "TIME" => {
-        let data: time::Time = row.get(i);
-        json!(data.to_string())
-      }
-      _ => Value::Null,
-    };
-    val.insert(col.name().to_string(), value);
-  });
-  Value::Object(val)
+/// the values are bound by their json type, mysql can't infer the parameter types, so the datetime
+/// and binary are bound by the typed parameters, the datetime is utc as mysql doesn't accept the offset
+fn value_to_args(values: &[Value]) -> Result<MySqlArguments> {
+  let mut args = MySqlArguments::default();
+  for (i, v) in values.iter().enumerate() {
+    bind(&mut args, v).map_err(|err| bind_error(i, err))?;
+  }
+  Ok(args)
 }
 
-fn value_to_args(val: Option<&Value>) -> Option<MySqlArguments> {
-  match val {
-    Some(Value::Array(a)) => {
-      let mut args = MySqlArguments::default();
-      for v in a {
-        let _ = match v {
-          Value::Bool(b) => args.add(*b),
-          Value::String(s) => args.add(s.as_str()),
-          Value::Number(n) => {
-            if n.is_f64() {
-              args.add(n.as_f64().unwrap_or_default())
-            } else {
-              args.add(n.as_i64().unwrap_or_default())
-            }
-          }
-          _ => Ok(()),
-        };
-      }
-      Some(args)
+fn bind(args: &mut MySqlArguments, v: &Value) -> Result<()> {
+  let result = match (v, TypedParam::from_value(v)) {
+    (_, Some(TypedParam::Datetime(d))) => {
+      args.add(to_utc_datetime(d).ok_or_else(|| unsupported(d, "DATETIME"))?)
     }
-    _ => None,
+    (_, Some(TypedParam::Blob(b))) => args.add(to_bytes(b).ok_or_else(|| unsupported(b, "BLOB"))?),
+    (Value::Null, _) => args.add(None::<String>),
+    (Value::Bool(b), _) => args.add(*b),
+    (Value::Number(n), _) => match (n.as_i64(), n.as_u64()) {
+      (Some(n), _) => args.add(n),
+      (None, Some(n)) => args.add(n),
+      _ => args.add(n.as_f64().unwrap_or_default()),
+    },
+    (Value::String(s), _) => args.add(s.as_str()),
+    (Value::Object(_) | Value::Array(_), _) => args.add(Json(v)),
+  };
+  result.map_err(|err| anyhow!(err))
+}
+
+/// mysql has one schema per database, the tables of the current database are listed
+const SCHEMA_TABLES: &str = r#"
+SELECT NULL AS `schema`, TABLE_NAME AS name, IF(TABLE_TYPE = 'VIEW', 'view', 'table') AS type,
+  TABLE_ROWS AS `rows`, NULLIF(TABLE_COMMENT, '') AS comment
+FROM information_schema.TABLES
+WHERE TABLE_SCHEMA = DATABASE()
+ORDER BY TABLE_NAME
+"#;
+
+const SCHEMA_COLUMNS: &str = r#"
+SELECT NULL AS `schema`, TABLE_NAME AS `table`, COLUMN_NAME AS name, COLUMN_TYPE AS type,
+  IS_NULLABLE = 'YES' AS nullable, COLUMN_DEFAULT AS `default`,
+  NULLIF(COLUMN_COMMENT, '') AS comment, COLUMN_KEY = 'PRI' AS pk
+FROM information_schema.COLUMNS
+WHERE TABLE_SCHEMA = DATABASE()
+ORDER BY TABLE_NAME, ORDINAL_POSITION
+"#;
+
+const SCHEMA_FOREIGN_KEYS: &str = r#"
+SELECT NULL AS `schema`, TABLE_NAME AS `table`, CONSTRAINT_NAME AS name, COLUMN_NAME AS `column`,
+  NULLIF(REFERENCED_TABLE_SCHEMA, DATABASE()) AS refSchema, REFERENCED_TABLE_NAME AS refTable,
+  REFERENCED_COLUMN_NAME AS refColumn
+FROM information_schema.KEY_COLUMN_USAGE
+WHERE TABLE_SCHEMA = DATABASE() AND REFERENCED_TABLE_NAME IS NOT NULL
+ORDER BY TABLE_NAME, CONSTRAINT_NAME, ORDINAL_POSITION
+"#;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_upsert_clause() {
+    assert!(supports_row_alias("8.0.35"));
+    assert!(supports_row_alias("8.4.0-log"));
+    assert!(!supports_row_alias("8.0.18"));
+    assert!(!supports_row_alias("5.7.44"));
+    assert!(!supports_row_alias("11.4.2-MariaDB-ubu2404"));
+
+    let keys = ["id".to_string()];
+    let update = ["name".to_string(), "n".to_string()];
+    assert_eq!(
+      upsert_clause(&keys, &update, true),
+      " AS new ON DUPLICATE KEY UPDATE name = new.name, n = new.n"
+    );
+    assert_eq!(
+      upsert_clause(&keys, &update, false),
+      " ON DUPLICATE KEY UPDATE name = VALUES(name), n = VALUES(n)"
+    );
+    assert_eq!(
+      upsert_clause(&keys, &[], true),
+      " AS new ON DUPLICATE KEY UPDATE id = id"
+    );
   }
 }
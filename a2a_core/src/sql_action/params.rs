@@ -0,0 +1,41 @@
+use a2a_types::Value;
+use anyhow::{anyhow, Result};
+
+use super::lexer::{tokenize, Token};
+
+/// convert the named placeholders `:name` / `@name` to positional `?`,
+/// return the sql and the names in the order of the placeholders.
+///
+/// the placeholders inside quoted strings, identifiers and comments are kept,
+/// so are the postgres cast `::type` and the mysql system variable `@@name`
+pub(crate) fn named_to_positional(sql: &str, backslash_escapes: bool) -> (String, Vec<String>) {
+  let mut result = String::with_capacity(sql.len());
+  let mut names = Vec::new();
+  for token in tokenize(sql, backslash_escapes) {
+    match token {
+      Token::Named(name) => {
+        names.push(name[1..].to_string());
+        result.push('?');
+      }
+      token => result.push_str(token.text()),
+    }
+  }
+  (result, names)
+}
+
+/// the positional parameters of the object row by the names
+pub(crate) fn named_row_to_array(row: &Value, names: &[String], index: usize) -> Result<Value> {
+  let Value::Object(row) = row else {
+    return Err(anyhow!("Row {} is not an object", index));
+  };
+  names
+    .iter()
+    .map(|name| {
+      row
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("Missing parameter `{}` in row {}", name, index))
+    })
+    .collect::<Result<Vec<_>>>()
+    .map(Value::Array)
+}
@@ -1,161 +1,664 @@
-use a2a_tojson::bytes_to_json;
+use std::{borrow::Cow, str::FromStr};
+
 use a2a_types::{SqlAction, SqlActionResult, Value};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use futures::future::BoxFuture;
 use serde_json::json;
 use sqlx::{
-  postgres::{PgArguments, PgRow},
-  Arguments, Column, Connection, PgConnection, Row, TypeInfo,
+  error::BoxDynError,
+  postgres::{
+    types::{Oid, PgHstore, PgInterval, PgMoney, PgRange, PgTimeTz},
+    PgArguments, PgConnection, PgQueryResult, PgRow, PgTypeInfo, PgTypeKind,
+  },
+  types::{BigDecimal, Json, Uuid},
+  Arguments, Connection, Decode, Postgres, Type, TypeInfo,
 };
+use time::{Date, Time};
+use tracing::debug;
 
-use super::array_dim;
-
-pub(crate) async fn do_sql_action(action: SqlAction) -> Result<SqlActionResult> {
-  let mut conn = PgConnection::connect(&action.connection).await?;
-  let sql = &mysql_syntax_to_pgsql(&action.query);
-  match array_dim(action.rows.as_ref()) {
-    0 => {
-      // no bind parameters
-      let rows = sqlx::query(sql).fetch_all(&mut conn).await?;
-      let mut result = Vec::new();
-      for row in rows {
-        result.push(row_to_value(row));
-      }
-      Ok(SqlActionResult::Array(result))
+use super::{
+  decode::{bytes, decimal, get, ip_net, iso_duration, row_to_object, DecodeOptions},
+  encode::{
+    bind_error, data_url_bytes, to_bytes, to_date, to_datetime, to_f64, to_i64, to_ip_net, to_time,
+    to_utc_datetime, unsupported, TypedParam,
+  },
+  lexer::{tokenize, Token},
+  pool::pg_pool,
+  runner::{run_action, SqlDatabase},
+  schema::SchemaQueries,
+};
+
+/// the read-only action runs in a read-only transaction,
+/// postgres rejects the writes which pass the statement check, eg: `SELECT nextval('s')`
+pub(crate) async fn do_sql_action(action: SqlAction, read_only: bool) -> Result<SqlActionResult> {
+  let mut conn = pg_pool(&action.connection)?.acquire().await?;
+  if !read_only {
+    return run_action::<Postgres>(&mut conn, &action).await;
+  }
+  let mut tx = conn.begin_with("BEGIN READ ONLY").await?;
+  let result = run_action::<Postgres>(&mut tx, &action).await?;
+  tx.commit().await?;
+  Ok(result)
+}
+
+impl SqlDatabase for Postgres {
+  const DESCRIBE_PARAMS: bool = true;
+
+  const MAX_PARAMS: usize = u16::MAX as usize;
+
+  const SCHEMA: SchemaQueries = SchemaQueries {
+    tables: SCHEMA_TABLES,
+    columns: SCHEMA_COLUMNS,
+    foreign_keys: SCHEMA_FOREIGN_KEYS,
+    row_estimates: "",
+  };
+
+  fn row_to_value(row: PgRow, options: &DecodeOptions) -> Value {
+    row_to_value(row, options)
+  }
+
+  fn value_to_args(values: &[Value], types: Option<&[PgTypeInfo]>) -> Result<PgArguments> {
+    value_to_args(values, types)
+  }
+
+  fn rows_affected(result: &PgQueryResult) -> u64 {
+    result.rows_affected()
+  }
+
+  fn copy_in<'c>(
+    conn: &'c mut PgConnection,
+    sql: &'c str,
+    data: Vec<u8>,
+  ) -> BoxFuture<'c, Result<u64>> {
+    Box::pin(async move {
+      let mut copy = conn.copy_in_raw(sql).await?;
+      copy.send(data).await?;
+      Ok(copy.finish().await?)
+    })
+  }
+
+  fn rewrite_sql(sql: &str) -> Cow<'_, str> {
+    Cow::Owned(mysql_syntax_to_pgsql(sql))
+  }
+}
+
+fn row_to_value(row: PgRow, options: &DecodeOptions) -> Value {
+  row_to_object(&row, |row, i, type_info| decode(row, i, type_info, options))
+}
+
+fn decode(row: &PgRow, i: usize, type_info: &PgTypeInfo, options: &DecodeOptions) -> Option<Value> {
+  let value = match type_info.name() {
+    "BOOL" => json!(get::<bool, _>(row, i)?),
+    "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" | "UNKNOWN" => json!(get::<String, _>(row, i)?),
+    "\"CHAR\"" => json!((get::<i8, _>(row, i)? as u8 as char).to_string()),
+    "INT2" => json!(get::<i16, _>(row, i)?),
+    "INT4" => json!(get::<i32, _>(row, i)?),
+    "INT8" => json!(get::<i64, _>(row, i)?),
+    "OID" => json!(get::<Oid, _>(row, i)?.0),
+    "FLOAT4" => json!(get::<f32, _>(row, i)?),
+    "FLOAT8" => json!(get::<f64, _>(row, i)?),
+    "NUMERIC" => decimal(get(row, i)?),
+    "MONEY" => decimal(get::<PgMoney, _>(row, i)?.to_bigdecimal(2)),
+    "BYTEA" => bytes(get(row, i)?),
+    "JSON" | "JSONB" => get::<Value, _>(row, i)?,
+    "UUID" => json!(get::<Uuid, _>(row, i)?.to_string()),
+    "INET" | "CIDR" => ip_net(get(row, i)?),
+    "MACADDR" | "MACADDR8" => {
+      let data = get::<Vec<u8>, _>(row, i)?;
+      json!(data
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":"))
     }
-    1 => {
-      if let Some(args) = value_to_args(action.rows.as_ref()) {
-        let rows = sqlx::query_with(sql, args).fetch_all(&mut conn).await?;
-        let mut result = Vec::new();
-        for row in rows {
-          result.push(row_to_value(row));
-        }
-        Ok(SqlActionResult::Array(result))
-      } else {
-        anyhow::bail!("Unsupported bind parameters");
+    "TIMESTAMP" => options.primitive_datetime(get(row, i)?),
+    "TIMESTAMPTZ" => options.datetime(get(row, i)?),
+    "DATE" => json!(get::<Date, _>(row, i)?.to_string()),
+    "TIME" => json!(get::<Time, _>(row, i)?.to_string()),
+    "TIMETZ" => {
+      let t = get::<PgTimeTz, _>(row, i)?;
+      json!(format!("{}{}", t.time, t.offset))
+    }
+    "INTERVAL" => {
+      let t = get::<PgInterval, _>(row, i)?;
+      json!(iso_duration(t.months, t.days, t.microseconds))
+    }
+    "INT4RANGE" => json!(get::<PgRange<i32>, _>(row, i)?.to_string()),
+    "INT8RANGE" => json!(get::<PgRange<i64>, _>(row, i)?.to_string()),
+    "NUMRANGE" => json!(get::<PgRange<BigDecimal>, _>(row, i)?.to_string()),
+    "DATERANGE" => json!(get::<PgRange<Date>, _>(row, i)?.to_string()),
+    "HSTORE" => json!(get::<PgHstore, _>(row, i)?.0),
+    "VOID" => Value::Null,
+    "BOOL[]" => array::<bool>(row, i, |v| json!(v))?,
+    "INT2[]" => array::<i16>(row, i, |v| json!(v))?,
+    "INT4[]" => array::<i32>(row, i, |v| json!(v))?,
+    "INT8[]" => array::<i64>(row, i, |v| json!(v))?,
+    "FLOAT4[]" => array::<f32>(row, i, |v| json!(v))?,
+    "FLOAT8[]" => array::<f64>(row, i, |v| json!(v))?,
+    "TEXT[]" | "VARCHAR[]" | "CHAR[]" | "NAME[]" => array::<String>(row, i, Value::String)?,
+    "NUMERIC[]" => array(row, i, decimal)?,
+    "BYTEA[]" => array(row, i, bytes)?,
+    "JSON[]" | "JSONB[]" => array::<Value>(row, i, |v| v)?,
+    "UUID[]" => array::<Uuid>(row, i, |v| json!(v.to_string()))?,
+    "INET[]" | "CIDR[]" => array(row, i, ip_net)?,
+    "TIMESTAMP[]" => array(row, i, |v| options.primitive_datetime(v))?,
+    "TIMESTAMPTZ[]" => array(row, i, |v| options.datetime(v))?,
+    "DATE[]" => array::<Date>(row, i, |v| json!(v.to_string()))?,
+    "TIME[]" => array::<Time>(row, i, |v| json!(v.to_string()))?,
+    "INTERVAL[]" => array::<PgInterval>(row, i, |t| {
+      json!(iso_duration(t.months, t.days, t.microseconds))
+    })?,
+    _ => match type_info.kind() {
+      PgTypeKind::Enum(_) => json!(get::<String, _>(row, i)?),
+      PgTypeKind::Domain(base) => decode(row, i, base, options)?,
+      // the extension types like `citext`
+      _ if type_info.name().eq_ignore_ascii_case("citext") => json!(get::<String, _>(row, i)?),
+      _ => {
+        debug!(type_name = type_info.name(), "unsupported postgres type");
+        Value::Null
       }
+    },
+  };
+  Some(value)
+}
+
+/// the array column, the NULL items are `null`
+fn array<T>(row: &PgRow, i: usize, f: impl Fn(T) -> Value) -> Option<Value>
+where
+  T: for<'r> Decode<'r, Postgres> + Type<Postgres>,
+{
+  let items = get::<Vec<Option<T>>, _>(row, i)?;
+  Some(Value::Array(
+    items
+      .into_iter()
+      .map(|v| v.map(&f).unwrap_or(Value::Null))
+      .collect(),
+  ))
+}
+
+/// encode the values as the parameter types inferred by postgres, as the prepared statement is
+/// cached by the sql, the values of different types in the next call are still encoded as them
+fn value_to_args(values: &[Value], types: Option<&[PgTypeInfo]>) -> Result<PgArguments> {
+  let mut args = PgArguments::default();
+  for (i, v) in values.iter().enumerate() {
+    let typed = TypedParam::from_value(v);
+    let type_info = types
+      .and_then(|t| t.get(i))
+      .cloned()
+      .unwrap_or_else(|| value_type(v));
+    let result = match typed {
+      Some(TypedParam::Blob(b)) if type_info.name() == "BYTEA" => to_bytes(b)
+        .ok_or_else(|| unsupported(b, "BYTEA"))
+        .and_then(|data| args.add(data).map_err(|err| anyhow!(err))),
+      // the typed parameter is bound as the inferred type
+      Some(typed) => bind(&mut args, typed.value(), &type_info),
+      None => bind(&mut args, v, &type_info),
+    };
+    result.map_err(|err| bind_error(i, err))?;
+  }
+  Ok(args)
+}
+
+/// the parameter type by the value, when the type is unknown
+fn value_type(v: &Value) -> PgTypeInfo {
+  let name = match (v, TypedParam::from_value(v)) {
+    (_, Some(TypedParam::Datetime(_))) => "TIMESTAMPTZ",
+    (_, Some(TypedParam::Blob(_))) => "BYTEA",
+    (Value::Bool(_), _) => "BOOL",
+    (Value::Number(n), _) if n.is_i64() => "INT8",
+    (Value::Number(_), _) => "FLOAT8",
+    (Value::Object(_) | Value::Array(_), _) => "JSONB",
+    _ => "TEXT",
+  };
+  PgTypeInfo::with_name(name)
+}
+
+fn bind(args: &mut PgArguments, v: &Value, type_info: &PgTypeInfo) -> Result<()> {
+  let name = type_info.name();
+  let unsupported = || unsupported(v, name);
+  let result = match (name, v) {
+    (_, Value::Null) => args.add(None::<String>),
+    ("BOOL", Value::Bool(b)) => args.add(*b),
+    ("INT2", _) => args.add(
+      to_i64(v)
+        .and_then(|n| i16::try_from(n).ok())
+        .ok_or_else(unsupported)?,
+    ),
+    ("INT4", _) => args.add(
+      to_i64(v)
+        .and_then(|n| i32::try_from(n).ok())
+        .ok_or_else(unsupported)?,
+    ),
+    ("INT8", _) => args.add(to_i64(v).ok_or_else(unsupported)?),
+    ("FLOAT4", _) => args.add(to_f64(v).ok_or_else(unsupported)? as f32),
+    ("FLOAT8", _) => args.add(to_f64(v).ok_or_else(unsupported)?),
+    ("NUMERIC", Value::Number(_) | Value::String(_)) => {
+      let s = v.as_str().map(str::to_string).unwrap_or(v.to_string());
+      args.add(BigDecimal::from_str(s.trim()).map_err(|_| unsupported())?)
+    }
+    ("TEXT" | "VARCHAR" | "CHAR" | "NAME" | "UNKNOWN", Value::String(s)) => args.add(s.as_str()),
+    ("TEXT" | "VARCHAR" | "CHAR" | "NAME" | "UNKNOWN", _) => args.add(v.to_string()),
+    ("JSON", _) => args.add(v.to_string()),
+    ("JSONB", _) => args.add(Json(v)),
+    ("TIMESTAMPTZ", _) => args.add(to_datetime(v).ok_or_else(unsupported)?),
+    ("TIMESTAMP", _) => args.add(to_utc_datetime(v).ok_or_else(unsupported)?),
+    ("DATE", _) => args.add(to_date(v).ok_or_else(unsupported)?),
+    ("TIME", _) => args.add(to_time(v).ok_or_else(unsupported)?),
+    ("UUID", Value::String(s)) => args.add(Uuid::parse_str(s).map_err(|_| unsupported())?),
+    ("BYTEA", Value::String(s)) => {
+      args.add(data_url_bytes(s).unwrap_or_else(|| s.as_bytes().to_vec()))
+    }
+    ("INET" | "CIDR", _) => args.add(to_ip_net(v).ok_or_else(unsupported)?),
+    (_, Value::Array(items)) if name.ends_with("[]") => {
+      bind_array(args, items, name.trim_end_matches("[]")).ok_or_else(unsupported)?
     }
-    2 => {
-      if let Some(Value::Array(a)) = action.rows {
-        let mut result = Vec::new();
-        for row in a {
-          if let Some(args) = value_to_args(Some(&row)) {
-            let rows = sqlx::query_with(sql, args).fetch_all(&mut conn).await?;
-            for row in rows {
-              result.push(row_to_value(row));
-            }
-          }
+    _ => match type_info.kind() {
+      PgTypeKind::Enum(_) if v.is_string() => args.add(v.as_str()),
+      PgTypeKind::Domain(base) => return bind(args, v, base),
+      _ if name.eq_ignore_ascii_case("citext") => args.add(v.as_str().ok_or_else(unsupported)?),
+      _ => return Err(unsupported()),
+    },
+  };
+  result.map_err(|err| anyhow!(err))
+}
+
+/// the array of the element type, `None` if any item is not the type
+fn bind_array(
+  args: &mut PgArguments,
+  items: &[Value],
+  element_type: &str,
+) -> Option<Result<(), BoxDynError>> {
+  fn collect<T>(items: &[Value], f: impl Fn(&Value) -> Option<T>) -> Option<Vec<Option<T>>> {
+    items
+      .iter()
+      .map(|v| {
+        if v.is_null() {
+          Some(None)
+        } else {
+          f(v).map(Some)
         }
-        Ok(SqlActionResult::Array(result))
-      } else {
-        anyhow::bail!("Unsupported bind parameters");
+      })
+      .collect()
+  }
+  let result = match element_type {
+    "BOOL" => args.add(collect(items, Value::as_bool)?),
+    "INT2" => args.add(collect(items, |v| {
+      to_i64(v).and_then(|n| i16::try_from(n).ok())
+    })?),
+    "INT4" => args.add(collect(items, |v| {
+      to_i64(v).and_then(|n| i32::try_from(n).ok())
+    })?),
+    "INT8" => args.add(collect(items, to_i64)?),
+    "FLOAT4" => args.add(collect(items, |v| to_f64(v).map(|n| n as f32))?),
+    "FLOAT8" => args.add(collect(items, to_f64)?),
+    "TEXT" | "VARCHAR" | "CHAR" | "NAME" => {
+      args.add(collect(items, |v| v.as_str().map(str::to_string))?)
+    }
+    "UUID" => args.add(collect(items, |v| Uuid::parse_str(v.as_str()?).ok())?),
+    "TIMESTAMPTZ" => args.add(collect(items, to_datetime)?),
+    "DATE" => args.add(collect(items, to_date)?),
+    _ => return None,
+  };
+  Some(result)
+}
+
+/// rewrite the mysql style sql to postgres
+/// - the placeholders `?` to `$1`, `$2`, ..., the `?` in literals, comments and the jsonb operators
+///   `?|`, `?&` are kept, a `?` between an operand and a string, identifier or array is the jsonb
+///   operator too, `??` is the escaped one
+/// - the mysql column types, attributes and table options, only in `CREATE` / `ALTER` statements
+fn mysql_syntax_to_pgsql(sql: &str) -> String {
+  let mut tokens = tokenize(sql, false);
+
+  let mut sn = 0;
+  let mut prev: Option<Token> = None;
+  for i in 0..tokens.len() {
+    let is_jsonb_operator = prev.as_ref().is_some_and(is_operand)
+      && tokens[i + 1..]
+        .iter()
+        .find(|t| t.is_significant())
+        .is_some_and(is_jsonb_key);
+    let token = &mut tokens[i];
+    match token {
+      Token::Param if !is_jsonb_operator => {
+        sn += 1;
+        *token = Token::Other(format!("${}", sn));
       }
+      Token::Other(s) if s == "??" => *token = Token::Other("?".to_string()),
+      _ => {}
     }
-    _ => anyhow::bail!("Unsupported rows value"),
+    if token.is_significant() {
+      prev = Some(token.clone());
+    }
+  }
+
+  let is_ddl = tokens
+    .iter()
+    .find(|t| t.is_significant())
+    .is_some_and(|t| t.is_word("CREATE") || t.is_word("ALTER"));
+  if is_ddl {
+    mysql_ddl_to_pgsql(&tokens)
+  } else {
+    tokens.iter().map(Token::text).collect()
   }
 }
 
-fn row_to_value(row: PgRow) -> Value {
-  let mut val = serde_json::Map::new();
-  row.columns().iter().enumerate().for_each(|(i, col)| {
-    let value = match col.type_info().name() {
-      "BOOL" => Value::Bool(row.get(i)),
-      "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" => Value::String(row.get(i)),
-      "SMALLINT" | "SMALLSERIAL" | "INT2" => {
-        let n: i16 = row.get(i);
-        json!(n)
-      }
-      "INT" | "SERIAL" | "INT4" => {
-        let n: i32 = row.get(i);
-        json!(n)
-      }
-      "BIGINT" | "BIGSERIAL" | "INT8" => {
-        let n: i64 = row.get(i);
-        json!(n)
-      }
-      "REAL" | "FLOAT4" => {
-        let n: f32 = row.get(i);
-        json!(n)
-      }
-      "DOUBLE PRECISION" | "FLOAT8" => {
-        let n: f64 = row.get(i);
-        json!(n)
-      }
-      "BYTEA" => {
-        let data: Vec<u8> = row.get(i);
-        bytes_to_json(bytes::Bytes::from(data), "", None).unwrap_or(Value::Null)
-      }
-      "JSON" | "JSONB" => {
-        let data: serde_json::Value = row.get(i);
-        data
-      }
-      "TIMESTAMP" => {
-        let data: time::PrimitiveDateTime = row.get(i);
-        json!(data.assume_utc().unix_timestamp_nanos() / 1_000_000)
+/// the keywords followed by a value, so the `?` after them is a placeholder
+const VALUE_KEYWORDS: &[&str] = &[
+  "SELECT",
+  "WHERE",
+  "AND",
+  "OR",
+  "NOT",
+  "IN",
+  "VALUES",
+  "SET",
+  "LIMIT",
+  "OFFSET",
+  "THEN",
+  "ELSE",
+  "WHEN",
+  "CASE",
+  "BY",
+  "LIKE",
+  "ILIKE",
+  "BETWEEN",
+  "IS",
+  "ON",
+  "AS",
+  "RETURNING",
+  "HAVING",
+  "DISTINCT",
+  "ALL",
+  "ANY",
+  "SOME",
+  "EXISTS",
+  "INTERVAL",
+  "DEFAULT",
+  "ESCAPE",
+  "FETCH",
+  "FROM",
+  "TO",
+  "WITH",
+  "DO",
+  "ROWS",
+];
+
+/// the keywords following a value, eg: `FETCH NEXT ? ROWS ONLY`, `RANGE ? PRECEDING`
+const TRAILING_KEYWORDS: &[&str] = &[
+  "ROWS",
+  "ROW",
+  "ONLY",
+  "PRECEDING",
+  "FOLLOWING",
+  "ASC",
+  "DESC",
+  "NULLS",
+  "END",
+  "GROUP",
+  "ORDER",
+  "UNION",
+  "EXCEPT",
+  "INTERSECT",
+  "FOR",
+  "WINDOW",
+  "RANGE",
+  "AT",
+  "COLLATE",
+  "ISNULL",
+  "NOTNULL",
+  "PERCENT",
+];
+
+/// the right side of the jsonb `?` operator, a string, an identifier or an array
+fn is_jsonb_key(token: &Token) -> bool {
+  match token {
+    Token::Str(_) | Token::QuotedIdent(_) => true,
+    Token::Word(w) => !VALUE_KEYWORDS
+      .iter()
+      .chain(TRAILING_KEYWORDS)
+      .any(|k| w.eq_ignore_ascii_case(k)),
+    _ => false,
+  }
+}
+
+fn is_operand(token: &Token) -> bool {
+  match token {
+    Token::Word(w) => !VALUE_KEYWORDS.iter().any(|k| w.eq_ignore_ascii_case(k)),
+    Token::Number(_) | Token::Str(_) | Token::QuotedIdent(_) | Token::Param => true,
+    Token::Other(s) => s == ")" || s == "]" || s.starts_with('$'),
+    _ => false,
+  }
+}
+
+const INTEGER_TYPES: &[&str] = &[
+  "INT",
+  "INTEGER",
+  "BIGINT",
+  "SMALLINT",
+  "TINYINT",
+  "MEDIUMINT",
+];
+
+const TABLE_OPTIONS: &[&str] = &[
+  "ENGINE",
+  "DEFAULT",
+  "CHARSET",
+  "CHARACTER",
+  "COLLATE",
+  "AUTO_INCREMENT",
+  "COMMENT",
+  "ROW_FORMAT",
+];
+
+fn pgsql_type(mysql_type: &str) -> Option<&'static str> {
+  let t = match mysql_type.to_uppercase().as_str() {
+    "DATETIME" => "TIMESTAMP",
+    "TINYINT" => "SMALLINT",
+    "MEDIUMINT" => "INTEGER",
+    "TINYTEXT" | "MEDIUMTEXT" | "LONGTEXT" => "TEXT",
+    "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => "BYTEA",
+    "DOUBLE" => "DOUBLE PRECISION",
+    _ => return None,
+  };
+  Some(t)
+}
+
+/// translate the mysql ddl, eg:
+/// `` CREATE TABLE `t` (id INT(11) UNSIGNED AUTO_INCREMENT, at DATETIME) ENGINE=InnoDB `` =>
+/// `CREATE TABLE "t" (id INT GENERATED BY DEFAULT AS IDENTITY, at TIMESTAMP)`
+fn mysql_ddl_to_pgsql(tokens: &[Token]) -> String {
+  let next_significant = |from: usize| (from..tokens.len()).find(|i| tokens[*i].is_significant());
+  let is_create = next_significant(0).is_some_and(|n| tokens[n].is_word("CREATE"));
+  let mut result = String::new();
+  let mut prev: Option<&Token> = None;
+  let mut depth = 0;
+  let mut i = 0;
+  while i < tokens.len() {
+    let token = &tokens[i];
+    let next = next_significant(i + 1).map(|n| &tokens[n]);
+    // the column type follows the column name
+    let is_type_position = matches!(prev, Some(Token::Word(_) | Token::QuotedIdent(_)))
+      && !prev.is_some_and(|p| p.is_word("ADD") || p.is_word("COLUMN") || p.is_word("TABLE"));
+    match token {
+      Token::QuotedIdent(s) if s.starts_with('`') => {
+        let name = s.trim_start_matches('`').trim_end_matches('`');
+        result.push_str(&format!(
+          "\"{}\"",
+          name.replace("``", "`").replace('"', "\"\"")
+        ));
       }
-      "TIMESTAMPTZ" => {
-        let data: time::OffsetDateTime = row.get(i);
-        json!(data.unix_timestamp_nanos() / 1_000_000)
+      Token::Word(w)
+        if w.eq_ignore_ascii_case("AUTO_INCREMENT") || w.eq_ignore_ascii_case("AUTOINCREMENT") =>
+      {
+        result.push_str("GENERATED BY DEFAULT AS IDENTITY");
       }
-      "DATE" => {
-        let data: time::Date = row.get(i);
-        json!(data.to_string())
+      Token::Word(w)
+        if w.eq_ignore_ascii_case("UNSIGNED") || w.eq_ignore_ascii_case("ZEROFILL") =>
+      {
+        result.truncate(result.trim_end().len());
       }
-      "TIME" => {
-        let data: time::Time = row.get(i);
-        json!(data.to_string())
+      Token::Word(w)
+        if is_type_position && INTEGER_TYPES.iter().any(|t| w.eq_ignore_ascii_case(t)) =>
+      {
+        result.push_str(pgsql_type(w).unwrap_or(w));
+        // drop the display width, eg: `INT(11)`
+        let width = next_significant(i + 1)
+          .filter(|n| tokens[*n].text() == "(")
+          .and_then(|n| next_significant(n + 1))
+          .filter(|n| matches!(tokens[*n], Token::Number(_)))
+          .and_then(|n| next_significant(n + 1))
+          .filter(|n| tokens[*n].text() == ")");
+        if let Some(end) = width {
+          i = end;
+        }
       }
-      "HSTORE" => {
-        let data: serde_json::Value = row.get(i);
-        data
+      Token::Word(w) if is_type_position && pgsql_type(w).is_some() => match pgsql_type(w) {
+        Some(t) if t == "DOUBLE PRECISION" && next.is_some_and(|n| n.is_word("PRECISION")) => {
+          result.push_str(w)
+        }
+        Some(t) => result.push_str(t),
+        None => result.push_str(w),
+      },
+      Token::Other(s) if s == "(" => {
+        depth += 1;
+        result.push_str(s);
       }
-      _ => Value::Null,
-    };
-    val.insert(col.name().to_string(), value);
-  });
-  Value::Object(val)
-}
-
-fn value_to_args(val: Option<&Value>) -> Option<PgArguments> {
-  match val {
-    Some(Value::Array(a)) => {
-      let mut args = PgArguments::default();
-      for v in a {
-        let _ = match v {
-          Value::Bool(b) => args.add(*b),
-          Value::String(s) => args.add(s.as_str()),
-          Value::Number(n) => {
-            if n.is_f64() {
-              args.add(n.as_f64().unwrap_or_default())
-            } else {
-              args.add(n.as_i64().unwrap_or_default())
-            }
-          }
-          _ => Ok(()),
-        };
+      Token::Other(s) if s == ")" => {
+        depth -= 1;
+        result.push_str(s);
+        // drop the table options till the end of the statement
+        let is_table_options = next.is_some_and(|n| TABLE_OPTIONS.iter().any(|o| n.is_word(o)));
+        if is_create && depth == 0 && is_table_options {
+          i = (i + 1..tokens.len())
+            .find(|n| tokens[*n].text() == ";")
+            .unwrap_or(tokens.len());
+          prev = Some(token);
+          continue;
+        }
       }
-      Some(args)
+      _ => result.push_str(token.text()),
+    }
+    if token.is_significant() {
+      prev = Some(token);
     }
-    _ => None,
+    i += 1;
   }
+  result
 }
 
-fn mysql_syntax_to_pgsql(sql: &str) -> String {
-  // replace ? to $1, $2, ...
-  let mut sn = 1;
-  let mut result = String::new();
-  for c in sql.chars() {
-    if c == '?' {
-      result.push_str(&format!("${}", sn));
-      sn += 1;
-    } else {
-      result.push(c);
-    }
+/// the tables, partitioned tables, views and materialized views of the user schemas
+const SCHEMA_TABLES: &str = r#"
+SELECT n.nspname::text AS schema, c.relname::text AS name,
+  CASE WHEN c.relkind IN ('v', 'm') THEN 'view' ELSE 'table' END AS type,
+  CASE WHEN c.relkind IN ('r', 'p') AND c.reltuples >= 0 THEN c.reltuples::bigint END AS "rows",
+  obj_description(c.oid, 'pg_class') AS comment
+FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace
+WHERE c.relkind IN ('r', 'p', 'v', 'm') AND NOT c.relispartition
+  AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+ORDER BY 1, 2
+"#;
+
+const SCHEMA_COLUMNS: &str = r#"
+SELECT n.nspname::text AS schema, c.relname::text AS "table", a.attname::text AS name,
+  format_type(a.atttypid, a.atttypmod) AS type, NOT a.attnotnull AS nullable,
+  pg_get_expr(d.adbin, d.adrelid) AS "default", col_description(c.oid, a.attnum) AS comment,
+  EXISTS (
+    SELECT 1 FROM pg_constraint p
+    WHERE p.conrelid = c.oid AND p.contype = 'p' AND a.attnum = ANY (p.conkey)
+  ) AS pk
+FROM pg_class c
+JOIN pg_namespace n ON n.oid = c.relnamespace
+JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+LEFT JOIN pg_attrdef d ON d.adrelid = c.oid AND d.adnum = a.attnum
+WHERE c.relkind IN ('r', 'p', 'v', 'm') AND NOT c.relispartition
+  AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+ORDER BY 1, 2, a.attnum
+"#;
+
+const SCHEMA_FOREIGN_KEYS: &str = r#"
+SELECT n.nspname::text AS schema, c.relname::text AS "table", k.conname::text AS name,
+  a.attname::text AS "column", fn.nspname::text AS "refSchema", fc.relname::text AS "refTable",
+  fa.attname::text AS "refColumn"
+FROM pg_constraint k
+JOIN pg_class c ON c.oid = k.conrelid
+JOIN pg_namespace n ON n.oid = c.relnamespace
+JOIN pg_class fc ON fc.oid = k.confrelid
+JOIN pg_namespace fn ON fn.oid = fc.relnamespace
+CROSS JOIN unnest(k.conkey, k.confkey) WITH ORDINALITY AS u(attnum, refattnum, i)
+JOIN pg_attribute a ON a.attrelid = k.conrelid AND a.attnum = u.attnum
+JOIN pg_attribute fa ON fa.attrelid = k.confrelid AND fa.attnum = u.refattnum
+WHERE k.contype = 'f'
+  AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname NOT LIKE 'pg_toast%'
+ORDER BY 1, 2, 3, u.i
+"#;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_placeholders() {
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT * FROM t WHERE id = ? AND name IN (?, ?) LIMIT ?"),
+      "SELECT * FROM t WHERE id = $1 AND name IN ($2, $3) LIMIT $4"
+    );
+    // literals and comments
+    assert_eq!(
+      mysql_syntax_to_pgsql(
+        "SELECT '?', 'it''s ?', E'\\'?', $$ ? $$, \"?\" -- ?\nFROM t /* ? */ WHERE a = ?"
+      ),
+      "SELECT '?', 'it''s ?', E'\\'?', $$ ? $$, \"?\" -- ?\nFROM t /* ? */ WHERE a = $1"
+    );
+    // jsonb operators
+    assert_eq!(
+      mysql_syntax_to_pgsql(
+        "SELECT * FROM t WHERE data ? 'a' AND data ?| array['b'] AND data ?& ? AND data->'c' ?? ?"
+      ),
+      "SELECT * FROM t WHERE data ? 'a' AND data ?| array['b'] AND data ?& $1 AND data->'c' ? $2"
+    );
+    // the placeholders after the words which are not followed by a jsonb key
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT * FROM t ORDER BY id OFFSET ? ROWS FETCH NEXT ? ROWS ONLY"),
+      "SELECT * FROM t ORDER BY id OFFSET $1 ROWS FETCH NEXT $2 ROWS ONLY"
+    );
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT * FROM t FETCH FIRST ? ROWS ONLY"),
+      "SELECT * FROM t FETCH FIRST $1 ROWS ONLY"
+    );
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT at AT TIME ZONE ? FROM t WHERE at AT TIME ZONE ? > ?"),
+      "SELECT at AT TIME ZONE $1 FROM t WHERE at AT TIME ZONE $2 > $3"
+    );
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT sum(x) OVER (ORDER BY d RANGE ? PRECEDING) FROM t"),
+      "SELECT sum(x) OVER (ORDER BY d RANGE $1 PRECEDING) FROM t"
+    );
+    assert_eq!(
+      mysql_syntax_to_pgsql("SELECT data ? key, data ? \"k\", data ? ARRAY['a'] FROM t"),
+      "SELECT data ? key, data ? \"k\", data ? ARRAY['a'] FROM t"
+    );
+    // mysql-isms are kept outside ddl
+    assert_eq!(
+      mysql_syntax_to_pgsql("UPDATE t SET note = 'AUTO_INCREMENT' WHERE kind = ?"),
+      "UPDATE t SET note = 'AUTO_INCREMENT' WHERE kind = $1"
+    );
   }
-  // replace "AUTO_INCREMENT" to "GENERATED BY DEFAULT AS IDENTITY" ignore case
-  result = regex::Regex::new(r#"(?i)AUTO[\s_]?INCREMENT"#)
-    .unwrap()
-    .replace_all(&result, "GENERATED BY DEFAULT AS IDENTITY")
-    .to_string();
 
-  result
+  #[test]
+  fn test_ddl() {
+    assert_eq!(
+      mysql_syntax_to_pgsql(
+        "CREATE TABLE `user` (id INT(11) UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY, \
+         `datetime` DATETIME DEFAULT NULL, score DOUBLE, body LONGTEXT) \
+         ENGINE=InnoDB AUTO_INCREMENT=10 DEFAULT CHARSET=utf8mb4"
+      ),
+      "CREATE TABLE \"user\" (id INT NOT NULL GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, \
+       \"datetime\" TIMESTAMP DEFAULT NULL, score DOUBLE PRECISION, body TEXT)"
+    );
+    assert_eq!(
+      mysql_syntax_to_pgsql("ALTER TABLE t ADD COLUMN datetime DATETIME DEFAULT '-- ?'"),
+      "ALTER TABLE t ADD COLUMN datetime TIMESTAMP DEFAULT '-- ?'"
+    );
+  }
 }
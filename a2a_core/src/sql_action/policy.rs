@@ -0,0 +1,370 @@
+use std::fmt;
+
+use a2a_types::SqlAction;
+use anyhow::Result;
+
+use super::lexer::{statement_kind, tokenize, Token};
+
+/// the statements which only read, they are allowed by the read-only connection
+const READ_KINDS: &[&str] = &[
+  "SELECT", "VALUES", "TABLE", "SHOW", "DESCRIBE", "DESC", "EXPLAIN",
+];
+
+/// the words between the write keyword and its table, eg: `INSERT IGNORE INTO t`
+const TARGET_MODIFIERS: &[&str] = &[
+  "INTO",
+  "FROM",
+  "ONLY",
+  "IGNORE",
+  "LOW_PRIORITY",
+  "HIGH_PRIORITY",
+  "DELAYED",
+  "QUICK",
+];
+
+/// the words after the table of mysql multi-table `UPDATE` and `DELETE`, the other tables are written too
+const JOIN_WORDS: &[&str] = &[
+  "JOIN",
+  "INNER",
+  "LEFT",
+  "RIGHT",
+  "CROSS",
+  "NATURAL",
+  "STRAIGHT_JOIN",
+];
+
+/// the statements allowed by the connection, set by the parameters of the connection string,
+/// eg: `postgres://user@host/db?readOnly=true`, `mysql://user@host/db?allow=select,insert:orders`
+/// - readOnly : only the queries, eg: `SELECT`, `SHOW`, `EXPLAIN`,
+///   postgres also runs the action in a read-only transaction
+/// - allow : the statement kinds, the kind with a table is only allowed on the table, eg: `update:orders`
+#[derive(Debug, Default)]
+pub(crate) struct SqlPolicy {
+  pub read_only: bool,
+  allow: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+  kind: String,
+  table: Option<String>,
+}
+
+/// a read or write of the statement, eg: `SELECT`, `INSERT orders`
+#[derive(Debug, PartialEq)]
+struct Operation {
+  kind: String,
+  /// the written table, `None` if unknown
+  table: Option<String>,
+}
+
+impl fmt::Display for Operation {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match &self.table {
+      Some(table) => write!(f, "{} {}", self.kind, table),
+      None => write!(f, "{}", self.kind),
+    }
+  }
+}
+
+impl SqlPolicy {
+  /// take the policy parameters out of the connection string, the others are kept for the driver
+  pub(crate) fn from_connection(conn: &str) -> Result<(Self, String)> {
+    let mut policy = Self::default();
+    let Some((base, query)) = conn.split_once('?') else {
+      return Ok((policy, conn.to_string()));
+    };
+    let mut params = vec![];
+    for param in query.split('&') {
+      let (key, value) = param.split_once('=').unwrap_or((param, ""));
+      let value = urlencoding::decode(value)?;
+      match key {
+        "readOnly" | "read_only" => {
+          policy.read_only = match value.as_ref() {
+            "" | "true" | "1" => true,
+            "false" | "0" => false,
+            other => anyhow::bail!("Invalid readOnly `{}`, use true or false", other),
+          }
+        }
+        "allow" => {
+          for item in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (kind, table) = match item.split_once(':') {
+              Some((kind, table)) => (kind, Some(table.trim().to_string())),
+              None => (item, None),
+            };
+            policy.allow.push(Rule {
+              kind: kind.trim().to_uppercase(),
+              table,
+            });
+          }
+        }
+        _ => params.push(param),
+      }
+    }
+    let conn = match params.is_empty() {
+      true => base.to_string(),
+      false => format!("{}?{}", base, params.join("&")),
+    };
+    Ok((policy, conn))
+  }
+
+  /// check the query and the statements of the action before running them
+  pub(crate) fn check_action(&self, action: &SqlAction, backslash_escapes: bool) -> Result<()> {
+    if !self.read_only && self.allow.is_empty() {
+      return Ok(());
+    }
+    // the schema method only reads the catalog
+    if action.method.as_deref() == Some("schema") {
+      return Ok(());
+    }
+    self.check(&action.query, backslash_escapes)?;
+    for statement in action.statements.iter().flatten() {
+      self.check(&statement.query, backslash_escapes)?;
+    }
+    Ok(())
+  }
+
+  /// every statement of the sql must be allowed, the sql may have multiple statements separated by `;`
+  fn check(&self, sql: &str, backslash_escapes: bool) -> Result<()> {
+    let tokens = tokenize(sql, backslash_escapes);
+    for statement in tokens.split(|t| *t == Token::Other(";".to_string())) {
+      for op in operations(statement) {
+        if self.read_only && !READ_KINDS.contains(&op.kind.as_str()) {
+          anyhow::bail!("`{}` is not allowed by the read-only connection", op);
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|rule| rule.allows(&op)) {
+          anyhow::bail!("`{}` is not allowed by the connection", op);
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Rule {
+  /// the table of the rule without schema matches the table in any schema
+  fn allows(&self, op: &Operation) -> bool {
+    if self.kind != op.kind {
+      return false;
+    }
+    match (&self.table, &op.table) {
+      (None, _) => true,
+      (Some(_), None) => false,
+      (Some(rule), Some(table)) => {
+        let name = match rule.contains('.') {
+          true => table.as_str(),
+          false => table.rsplit('.').next().unwrap_or_default(),
+        };
+        name.eq_ignore_ascii_case(rule)
+      }
+    }
+  }
+}
+
+/// the kind of the statement and all the writes in it,
+/// eg: `WITH d AS (DELETE FROM a RETURNING *) SELECT * FROM d` is `SELECT` and `DELETE a`
+fn operations(tokens: &[Token]) -> Vec<Operation> {
+  let words = tokens
+    .iter()
+    .filter(|t| t.is_significant())
+    .collect::<Vec<_>>();
+  let Some(first) = words.iter().find_map(|t| match t {
+    Token::Word(w) => Some(w.to_uppercase()),
+    _ => None,
+  }) else {
+    return vec![];
+  };
+  let sql = tokens.iter().map(|t| t.text()).collect::<String>();
+  let kind = statement_kind(&sql).unwrap_or(first);
+
+  let mut ops = vec![];
+  for (i, token) in words.iter().enumerate() {
+    let Token::Word(word) = token else {
+      continue;
+    };
+    let word = word.to_uppercase();
+    let prev = i.checked_sub(1).map(|p| words[p]);
+    let after = |keywords: &[&str]| prev.is_some_and(|p| keywords.iter().any(|k| p.is_word(k)));
+    let next = words.get(i + 1);
+    let write = match word.as_str() {
+      // the actions of `MERGE`, on its target table
+      "INSERT" | "UPDATE" | "DELETE" if after(&["THEN"]) => false,
+      // `FOR UPDATE`, `ON DUPLICATE KEY UPDATE`, `ON CONFLICT DO UPDATE`, `ON DELETE CASCADE`
+      "UPDATE" => !after(&["FOR", "KEY", "DO", "ON"]),
+      "DELETE" => !after(&["ON"]),
+      "INSERT" | "MERGE" => true,
+      // not the `REPLACE()` function and sqlite `INSERT OR REPLACE`
+      "REPLACE" => next.is_some_and(|t| t.text() != "(") && !after(&["OR"]),
+      // `SELECT ... INTO` creates a table or writes a file
+      "INTO" => {
+        let insert = words[..i]
+          .iter()
+          .rev()
+          .take(3)
+          .any(|t| ["INSERT", "REPLACE", "MERGE"].iter().any(|k| t.is_word(k)));
+        if !insert {
+          ops.push(Operation {
+            kind: "INTO".to_string(),
+            table: None,
+          });
+        }
+        false
+      }
+      _ => false,
+    };
+    if write {
+      ops.push(Operation {
+        kind: word,
+        table: target_table(&words[i + 1..]),
+      });
+    }
+  }
+  if !ops.iter().any(|op| op.kind == kind) {
+    ops.insert(0, Operation { kind, table: None });
+  }
+  ops
+}
+
+/// the table written by the statement, `None` if it writes multiple tables
+fn target_table(words: &[&Token]) -> Option<String> {
+  let mut i = 0;
+  while let Some(token) = words.get(i) {
+    if token.is_word("OR") {
+      // sqlite `UPDATE OR IGNORE`, `INSERT OR REPLACE`
+      i += 2;
+    } else if TARGET_MODIFIERS.iter().any(|m| token.is_word(m)) {
+      i += 1;
+    } else {
+      break;
+    }
+  }
+
+  let mut parts = vec![];
+  while let Some(token) = words.get(i) {
+    let part = match token {
+      Token::Word(w) => w.clone(),
+      Token::QuotedIdent(q) => q[1..q.len() - 1].to_string(),
+      _ => break,
+    };
+    parts.push(part);
+    if words.get(i + 1).is_some_and(|t| t.text() == ".") {
+      i += 2;
+    } else {
+      i += 1;
+      break;
+    }
+  }
+  if parts.is_empty() || parts.iter().any(|p| p.eq_ignore_ascii_case("SET")) {
+    return None;
+  }
+
+  // skip the alias, eg: `UPDATE t AS a` or `DELETE t FROM t`
+  if words.get(i).is_some_and(|t| t.is_word("AS")) {
+    i += 2;
+  } else if matches!(words.get(i), Some(Token::Word(w)) if !is_clause(w) && !is_join(w)) {
+    i += 1;
+  }
+  let multiple = words
+    .get(i)
+    .is_some_and(|t| t.text() == "," || is_join(t.text()));
+  (!multiple).then(|| parts.join("."))
+}
+
+fn is_join(word: &str) -> bool {
+  JOIN_WORDS.iter().any(|w| word.eq_ignore_ascii_case(w))
+}
+
+/// the keywords after the table of a write statement, they are not an alias
+fn is_clause(word: &str) -> bool {
+  [
+    "SET",
+    "VALUES",
+    "VALUE",
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "USING",
+    "DEFAULT",
+    "RETURNING",
+    "WITH",
+    "ORDER",
+    "LIMIT",
+    "ON",
+    "PARTITION",
+  ]
+  .iter()
+  .any(|k| word.eq_ignore_ascii_case(k))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ops(sql: &str) -> Vec<String> {
+    operations(&tokenize(sql, false))
+      .iter()
+      .map(|op| op.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_operations() {
+    assert_eq!(
+      ops("select replace(name, 'a', 'b') from t for update"),
+      vec!["SELECT"]
+    );
+    assert_eq!(
+      ops("WITH d AS (DELETE FROM a RETURNING *) SELECT * FROM d"),
+      vec!["SELECT", "DELETE a"]
+    );
+    assert_eq!(
+      ops("INSERT INTO public.\"Orders\" (id) VALUES (?) ON CONFLICT (id) DO UPDATE SET id = 1"),
+      vec!["INSERT public.Orders"]
+    );
+    assert_eq!(ops("INSERT OR REPLACE INTO t VALUES (1)"), vec!["INSERT t"]);
+    assert_eq!(
+      ops("update low_priority t AS x set a = 1"),
+      vec!["UPDATE t"]
+    );
+    assert_eq!(
+      ops("UPDATE t1 JOIN t2 ON t1.id = t2.id SET t2.a = 1"),
+      vec!["UPDATE"]
+    );
+    assert_eq!(ops("DELETE t1, t2 FROM t1 JOIN t2"), vec!["DELETE"]);
+    assert_eq!(ops("SELECT * INTO backup FROM t"), vec!["SELECT", "INTO"]);
+    assert_eq!(
+      ops("EXPLAIN ANALYZE DELETE FROM t"),
+      vec!["EXPLAIN", "DELETE t"]
+    );
+    assert_eq!(ops("DROP TABLE t"), vec!["DROP"]);
+    assert_eq!(ops(" -- comment only"), Vec::<String>::new());
+  }
+
+  #[test]
+  fn test_policy() {
+    let (policy, conn) =
+      SqlPolicy::from_connection("postgres://u@h/db?sslmode=disable&readOnly=true").unwrap();
+    assert!(policy.read_only);
+    assert_eq!(conn, "postgres://u@h/db?sslmode=disable");
+    assert!(policy.check("SELECT 1; SHOW tables", false).is_ok());
+    assert!(policy.check("SELECT 1; DROP TABLE t", false).is_err());
+
+    let (policy, conn) =
+      SqlPolicy::from_connection("db.sqlite?allow=select,insert:orders,update%3Aorders").unwrap();
+    assert!(!policy.read_only);
+    assert_eq!(conn, "db.sqlite");
+    assert!(policy
+      .check("INSERT INTO main.orders VALUES (1)", false)
+      .is_ok());
+    assert!(policy.check("UPDATE \"ORDERS\" SET a = 1", false).is_ok());
+    assert!(policy.check("DELETE FROM orders", false).is_err());
+    assert!(policy.check("INSERT INTO users VALUES (1)", false).is_err());
+    // the write hidden in the query
+    assert!(policy
+      .check(
+        "WITH d AS (DELETE FROM orders RETURNING *) SELECT * FROM d",
+        false
+      )
+      .is_err());
+  }
+}
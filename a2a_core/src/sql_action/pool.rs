@@ -0,0 +1,137 @@
+use std::{
+  collections::HashMap,
+  str::FromStr,
+  sync::{LazyLock, Mutex},
+  time::Duration,
+};
+
+use anyhow::Result;
+use reqwest::Url;
+use sqlx::{
+  mysql::MySqlConnectOptions, pool::PoolOptions, postgres::PgConnectOptions,
+  sqlite::SqliteConnectOptions, Database, MySql, MySqlPool, PgPool, Pool, Postgres, Sqlite,
+  SqlitePool,
+};
+use tracing::debug;
+
+/// process wide pool config, from the environment variables
+/// - `A2A_SQL_POOL_MAX_SIZE` : max connections of each pool, default is 5
+/// - `A2A_SQL_POOL_IDLE_TIMEOUT` : seconds to close the idle connection, default is 300
+struct PoolConfig {
+  max_size: u32,
+  idle_timeout: Duration,
+}
+
+static POOL_CONFIG: LazyLock<PoolConfig> = LazyLock::new(|| {
+  let env = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+  PoolConfig {
+    max_size: env("A2A_SQL_POOL_MAX_SIZE").unwrap_or(5).max(1) as u32,
+    idle_timeout: Duration::from_secs(env("A2A_SQL_POOL_IDLE_TIMEOUT").unwrap_or(300)),
+  }
+});
+
+static MYSQL_POOLS: LazyLock<Mutex<HashMap<String, MySqlPool>>> = LazyLock::new(Default::default);
+static PG_POOLS: LazyLock<Mutex<HashMap<String, PgPool>>> = LazyLock::new(Default::default);
+static SQLITE_POOLS: LazyLock<Mutex<HashMap<String, SqlitePool>>> = LazyLock::new(Default::default);
+
+fn pool_options<DB: Database>() -> PoolOptions<DB> {
+  PoolOptions::<DB>::new()
+    .max_connections(POOL_CONFIG.max_size)
+    .idle_timeout(POOL_CONFIG.idle_timeout)
+}
+
+/// get the cached pool or create a new one, the connections are created lazily when acquired
+fn cached_pool<DB: Database>(
+  pools: &Mutex<HashMap<String, Pool<DB>>>,
+  key: String,
+  create: impl FnOnce() -> Result<Pool<DB>>,
+) -> Result<Pool<DB>> {
+  let mut pools = pools.lock().unwrap_or_else(|e| e.into_inner());
+  if let Some(pool) = pools.get(&key).filter(|p| !p.is_closed()) {
+    return Ok(pool.clone());
+  }
+  debug!(key, "create sql pool");
+  let pool = create()?;
+  pools.insert(key, pool.clone());
+  Ok(pool)
+}
+
+pub(crate) fn mysql_pool(conn: &str) -> Result<Pool<MySql>> {
+  cached_pool(&MYSQL_POOLS, normalize_url(conn, 3306), || {
+    let options = MySqlConnectOptions::from_str(conn)?;
+    Ok(pool_options().connect_lazy_with(options))
+  })
+}
+
+pub(crate) fn pg_pool(conn: &str) -> Result<Pool<Postgres>> {
+  cached_pool(&PG_POOLS, normalize_url(conn, 5432), || {
+    let options = PgConnectOptions::from_str(conn)?;
+    Ok(pool_options().connect_lazy_with(options))
+  })
+}
+
+pub(crate) fn sqlite_pool(filename: &str) -> Result<Pool<Sqlite>> {
+  let options = SqliteConnectOptions::new()
+    .filename(filename)
+    .create_if_missing(true);
+  if filename.is_empty() || filename.contains(":memory:") {
+    // each connection of memory database is a new database, keep it private to the action
+    return Ok(
+      PoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_lazy_with(options),
+    );
+  }
+  let key = std::path::Path::new(filename)
+    .canonicalize()
+    .map(|p| p.to_string_lossy().to_string())
+    .unwrap_or(filename.to_string());
+  cached_pool(&SQLITE_POOLS, key, || {
+    Ok(pool_options().connect_lazy_with(options))
+  })
+}
+
+/// normalize the connection url as the pool key, so the same database shares one pool,
+/// eg: `mysql://root@LOCALHOST/db?b=1&a=2` => `mysql://root@localhost:3306/db?a=2&b=1`
+fn normalize_url(conn: &str, default_port: u16) -> String {
+  let Ok(mut url) = Url::parse(conn) else {
+    return conn.to_string();
+  };
+  if let Some(host) = url.host_str().map(|h| h.to_lowercase()) {
+    let _ = url.set_host(Some(&host));
+  }
+  if url.port().is_none() {
+    let _ = url.set_port(Some(default_port));
+  }
+  let mut pairs = url
+    .query_pairs()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect::<Vec<_>>();
+  pairs.sort();
+  if pairs.is_empty() {
+    url.set_query(None);
+  } else {
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+  }
+  url.set_fragment(None);
+  url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_normalize_url() {
+    assert_eq!(
+      normalize_url("mysql://root@LOCALHOST/db?b=1&a=2", 3306),
+      "mysql://root@localhost:3306/db?a=2&b=1"
+    );
+    assert_eq!(
+      normalize_url("postgres://u:p@db:5432/app", 5432),
+      normalize_url("postgres://u:p@db/app", 5432)
+    );
+  }
+}
@@ -0,0 +1,500 @@
+use std::borrow::Cow;
+
+use a2a_types::{SqlAction, SqlActionResult, Value};
+use anyhow::{anyhow, Result};
+use futures::{future::BoxFuture, TryStreamExt};
+use serde_json::json;
+use sqlx::{query::Query, Connection, Database, Either, Executor, IntoArguments, Statement};
+use tracing::{debug, warn};
+
+use super::{
+  array_dim,
+  bulk::{copy_csv_row, on_conflict, BulkMode, BulkOptions, InsertStatement},
+  decode::DecodeOptions,
+  export::{save_to, SaveTo},
+  lexer::{returns_rows, statement_kind},
+  params::{named_row_to_array, named_to_positional},
+  schema::{schema, SchemaQueries},
+};
+
+/// database specific conversions, the statements are run in the same way for all databases
+pub(crate) trait SqlDatabase: Database {
+  /// the string literals escape quote by backslash, eg: `'it\'s'`
+  const BACKSLASH_ESCAPES: bool = false;
+
+  /// the database infers the parameter types when preparing, eg: postgres
+  const DESCRIBE_PARAMS: bool = false;
+
+  /// the max bind parameters of a statement
+  const MAX_PARAMS: usize = 32766;
+
+  /// the queries of the `schema` method
+  const SCHEMA: SchemaQueries;
+
+  fn row_to_value(row: Self::Row, options: &DecodeOptions) -> Value;
+
+  /// bind the values, `types` are the parameter types when `DESCRIBE_PARAMS` is true
+  fn value_to_args<'q>(
+    values: &'q [Value],
+    types: Option<&[Self::TypeInfo]>,
+  ) -> Result<Self::Arguments<'q>>;
+
+  fn rows_affected(result: &Self::QueryResult) -> u64;
+
+  /// the auto increment id of the inserted row
+  fn last_insert_id(_result: &Self::QueryResult) -> Option<i64> {
+    None
+  }
+
+  /// the clause after the values of the upsert, `ON CONFLICT ... DO UPDATE` by default,
+  /// the connection is for the syntax of the server version
+  fn upsert_clause<'c>(
+    _conn: &'c mut Self::Connection,
+    keys: &[&str],
+    update: &[&str],
+  ) -> BoxFuture<'c, Result<String>> {
+    let clause = on_conflict(keys, update);
+    Box::pin(async move { Ok(clause) })
+  }
+
+  /// load the csv rows by `COPY ... FROM STDIN`, return the count of rows
+  fn copy_in<'c>(
+    _conn: &'c mut Self::Connection,
+    _sql: &'c str,
+    _data: Vec<u8>,
+  ) -> BoxFuture<'c, Result<u64>> {
+    Box::pin(async { anyhow::bail!("bulk copy is only supported by postgres") })
+  }
+
+  /// rewrite the sql to the database syntax
+  fn rewrite_sql(sql: &str) -> Cow<'_, str> {
+    Cow::Borrowed(sql)
+  }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StatementResult {
+  pub rows: Vec<Value>,
+  pub rows_affected: u64,
+  pub last_insert_id: Option<i64>,
+  /// the statement returns rows, eg: `SELECT` or a write with `RETURNING`
+  pub returns_rows: bool,
+}
+
+impl StatementResult {
+  /// aggregate the result of the batch rows, the last insert id is of the last row
+  pub(crate) fn extend(&mut self, other: StatementResult) {
+    self.rows.extend(other.rows);
+    self.rows_affected += other.rows_affected;
+    self.last_insert_id = other.last_insert_id.or(self.last_insert_id);
+  }
+
+  /// the rows of a query, or the metadata of a write statement
+  fn into_json(self) -> Value {
+    if self.returns_rows || !self.rows.is_empty() {
+      Value::Array(self.rows)
+    } else {
+      json!({"rowsAffected": self.rows_affected, "lastInsertId": self.last_insert_id})
+    }
+  }
+}
+
+pub(crate) async fn run_action<DB>(
+  conn: &mut DB::Connection,
+  action: &SqlAction,
+) -> Result<SqlActionResult>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  if SqlMethod::from_action(action)? == SqlMethod::Schema {
+    return schema::<DB>(conn).await;
+  }
+
+  let options = DecodeOptions::from_action(action)?;
+  if let Some(save) = action.save_to.as_ref() {
+    if action.statements.is_some() {
+      anyhow::bail!("saveTo only supports `query`, not `statements`");
+    }
+    let save = SaveTo::from_value(save)?;
+    return save_to::<DB>(conn, &action.query, action.rows.as_ref(), &save, &options).await;
+  }
+  // the bulk options are for `query`, the statements load the batch rows by the multi-row `VALUES`
+  let bulk = match action.statements {
+    Some(_) => BulkOptions::default(),
+    None => BulkOptions::from_action(action)?,
+  };
+  let (statements, transaction) = action_statements(action);
+
+  let results = if transaction {
+    let mut tx = conn.begin().await?;
+    match run_statements::<DB>(&mut tx, &statements, &options, &bulk).await {
+      Ok(results) => {
+        tx.commit().await?;
+        results
+      }
+      Err(err) => {
+        debug!(%err, "rollback sql transaction");
+        // the rollback failure should not hide the error of the statements
+        if let Err(rollback_err) = tx.rollback().await {
+          warn!(%err, %rollback_err, "rollback sql transaction failed");
+        }
+        return Err(err);
+      }
+    }
+  } else {
+    run_statements::<DB>(conn, &statements, &options, &bulk).await?
+  };
+  Ok(action_result(action, results))
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum SqlMethod {
+  Query,
+  Schema,
+}
+
+impl SqlMethod {
+  pub(crate) fn from_action(action: &SqlAction) -> Result<Self> {
+    match action.method.as_deref().map(str::to_lowercase).as_deref() {
+      None | Some("query") => Ok(Self::Query),
+      Some("schema") => Ok(Self::Schema),
+      Some(other) => anyhow::bail!(
+        "Unsupported sql method `{}`, use `query` or `schema`",
+        other
+      ),
+    }
+  }
+}
+
+/// the queries with their rows, and whether to run them in a transaction,
+/// multiple statements run in a transaction by default
+pub(crate) fn action_statements(action: &SqlAction) -> (Vec<(&str, Option<&Value>)>, bool) {
+  match action.statements.as_ref() {
+    Some(statements) => (
+      statements
+        .iter()
+        .map(|s| (s.query.as_str(), s.rows.as_ref()))
+        .collect::<Vec<_>>(),
+      action.transaction.unwrap_or(true),
+    ),
+    None => (
+      vec![(action.query.as_str(), action.rows.as_ref())],
+      action.transaction.unwrap_or(false),
+    ),
+  }
+}
+
+/// the result of each statement, or the result of the query
+pub(crate) fn action_result(action: &SqlAction, mut results: Vec<StatementResult>) -> Value {
+  if action.statements.is_some() {
+    Value::Array(
+      results
+        .into_iter()
+        .map(|r| {
+          json!({
+            "rowsAffected": r.rows_affected,
+            "lastInsertId": r.last_insert_id,
+            "rows": r.rows,
+          })
+        })
+        .collect(),
+    )
+  } else {
+    results.pop().unwrap_or_default().into_json()
+  }
+}
+
+async fn run_statements<DB>(
+  conn: &mut DB::Connection,
+  statements: &[(&str, Option<&Value>)],
+  options: &DecodeOptions,
+  bulk: &BulkOptions,
+) -> Result<Vec<StatementResult>>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let mut results = Vec::new();
+  for (i, (query, rows)) in statements.iter().enumerate() {
+    let result = run_query::<DB>(conn, query, *rows, options, bulk)
+      .await
+      .map_err(|err| anyhow!("statement {} failed: {}", i, err))?;
+    results.push(result);
+  }
+  Ok(results)
+}
+
+/// run the query with the rows, the 2-D rows run the query for each row
+async fn run_query<DB>(
+  conn: &mut DB::Connection,
+  query: &str,
+  rows: Option<&Value>,
+  options: &DecodeOptions,
+  bulk: &BulkOptions,
+) -> Result<StatementResult>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let mut result = run_rows::<DB>(conn, query, rows, options, bulk).await?;
+  result.returns_rows = returns_rows(query);
+  // the last insert id of sqlite is kept by the connection, it is stale for other statements
+  if !matches!(
+    statement_kind(query).as_deref(),
+    Some("INSERT") | Some("REPLACE")
+  ) {
+    result.last_insert_id = None;
+  }
+  Ok(result)
+}
+
+async fn run_rows<DB>(
+  conn: &mut DB::Connection,
+  query: &str,
+  rows: Option<&Value>,
+  options: &DecodeOptions,
+  bulk: &BulkOptions,
+) -> Result<StatementResult>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let (mut positional, rows) = positional_rows(query, rows, DB::BACKSLASH_ESCAPES)?;
+
+  match InsertStatement::parse(&positional, DB::BACKSLASH_ESCAPES) {
+    Some(insert) => {
+      let conflict = match bulk.upsert.as_ref() {
+        Some(upsert) => {
+          let (keys, update) = insert.upsert_columns(upsert)?;
+          DB::upsert_clause(&mut *conn, &keys, &update).await?
+        }
+        None => String::new(),
+      };
+      if bulk.mode == BulkMode::Copy || (bulk.mode == BulkMode::Values && rows.len() > 1) {
+        return bulk_insert::<DB>(conn, &insert, &conflict, &rows, bulk.mode, options).await;
+      }
+      if !conflict.is_empty() {
+        positional = Cow::Owned(insert.to_sql(1, &conflict));
+      }
+    }
+    None if bulk.upsert.is_some() => {
+      anyhow::bail!(
+        "upsert needs a single row insert, eg: `INSERT INTO t (id, name) VALUES (?, ?)`"
+      )
+    }
+    None => {}
+  }
+
+  let sql = DB::rewrite_sql(&positional);
+  if rows.is_empty() {
+    return fetch::<DB>(conn, &sql, None, options).await;
+  }
+  let mut result = StatementResult::default();
+  for row in rows {
+    result.extend(fetch::<DB>(conn, &sql, Some(&row), options).await?);
+  }
+  Ok(result)
+}
+
+/// insert the batch rows by the multi-row `VALUES` chunks or postgres `COPY`,
+/// the rows are inserted in a transaction, or a savepoint inside the transaction
+async fn bulk_insert<DB>(
+  conn: &mut DB::Connection,
+  insert: &InsertStatement,
+  conflict: &str,
+  rows: &[Cow<'_, Value>],
+  mode: BulkMode,
+  options: &DecodeOptions,
+) -> Result<StatementResult>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let rows = insert_rows(insert, rows)?;
+  let mut tx = conn.begin().await?;
+  let mut result = StatementResult::default();
+  if mode == BulkMode::Copy {
+    if !conflict.is_empty() {
+      anyhow::bail!("upsert is not supported by bulk copy");
+    }
+    let sql = insert.copy_sql()?;
+    let mut data = Vec::new();
+    rows.iter().for_each(|row| copy_csv_row(row, &mut data));
+    result.rows_affected = DB::copy_in(&mut tx, &sql, data).await?;
+  } else {
+    for chunk in rows.chunks(insert.chunk_rows(DB::MAX_PARAMS)) {
+      let sql = insert.to_sql(chunk.len(), conflict);
+      let params = Value::Array(chunk.iter().flat_map(|row| row.iter().cloned()).collect());
+      result.extend(fetch::<DB>(&mut tx, &DB::rewrite_sql(&sql), Some(&params), options).await?);
+    }
+  }
+  tx.commit().await?;
+  Ok(result)
+}
+
+/// the positional sql and the parameters of each row, the object rows are bound by the names
+pub(crate) fn positional_rows<'a>(
+  query: &'a str,
+  rows: Option<&'a Value>,
+  backslash_escapes: bool,
+) -> Result<(Cow<'a, str>, Vec<Cow<'a, Value>>)> {
+  Ok(match named_rows(rows) {
+    Some(named_rows) => {
+      let (positional, names) = named_to_positional(query, backslash_escapes);
+      if names.is_empty() {
+        anyhow::bail!("Object rows need named parameters like `:name` in the query");
+      }
+      let rows = named_rows
+        .into_iter()
+        .enumerate()
+        .map(|(i, row)| named_row_to_array(row, &names, i).map(Cow::Owned))
+        .collect::<Result<Vec<_>>>()?;
+      (Cow::Owned(positional), rows)
+    }
+    None => match (array_dim(rows), rows) {
+      // no bind parameters
+      (0, _) => (Cow::Borrowed(query), vec![]),
+      (1, Some(row)) => (Cow::Borrowed(query), vec![Cow::Borrowed(row)]),
+      (2, Some(Value::Array(a))) => (Cow::Borrowed(query), a.iter().map(Cow::Borrowed).collect()),
+      _ => anyhow::bail!("Unsupported rows value"),
+    },
+  })
+}
+
+/// the values of the batch rows, each row should have the values of the placeholders of the insert
+pub(crate) fn insert_rows<'a>(
+  insert: &InsertStatement,
+  rows: &'a [Cow<'_, Value>],
+) -> Result<Vec<&'a [Value]>> {
+  rows
+    .iter()
+    .enumerate()
+    .map(|(i, row)| match row.as_ref() {
+      Value::Array(a) if a.len() == insert.params() => Ok(a.as_slice()),
+      Value::Array(a) => Err(anyhow!(
+        "Row {} has {} values, the statement needs {}",
+        i,
+        a.len(),
+        insert.params()
+      )),
+      _ => Err(anyhow!(
+        "Unsupported bind parameters, each row should be an array"
+      )),
+    })
+    .collect()
+}
+
+/// the sql in the database syntax and the positional parameters of a single row,
+/// the row can be an array or an object for the named parameters
+pub(crate) fn single_row_query<DB: SqlDatabase>(
+  query: &str,
+  rows: Option<&Value>,
+) -> Result<(String, Option<Value>)> {
+  match rows {
+    Some(row @ Value::Object(_)) => {
+      let (positional, names) = named_to_positional(query, DB::BACKSLASH_ESCAPES);
+      if names.is_empty() {
+        anyhow::bail!("Object rows need named parameters like `:name` in the query");
+      }
+      let params = named_row_to_array(row, &names, 0)?;
+      Ok((DB::rewrite_sql(&positional).into_owned(), Some(params)))
+    }
+    _ if array_dim(rows) == 2 => anyhow::bail!("Only a single row of parameters is supported"),
+    _ => Ok((DB::rewrite_sql(query).into_owned(), rows.cloned())),
+  }
+}
+
+/// the rows bound by names, a object or an array of objects
+fn named_rows(rows: Option<&Value>) -> Option<Vec<&Value>> {
+  match rows {
+    Some(Value::Object(_)) => rows.map(|r| vec![r]),
+    Some(Value::Array(a)) if !a.is_empty() && a.iter().all(|r| r.is_object()) => {
+      Some(a.iter().collect())
+    }
+    _ => None,
+  }
+}
+
+/// the rows of the sql without parameters
+pub(crate) async fn query_rows<DB>(conn: &mut DB::Connection, sql: &str) -> Result<Vec<Value>>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  Ok(
+    fetch::<DB>(conn, sql, None, &DecodeOptions::default())
+      .await?
+      .rows,
+  )
+}
+
+async fn fetch<DB>(
+  conn: &mut DB::Connection,
+  sql: &str,
+  params: Option<&Value>,
+  options: &DecodeOptions,
+) -> Result<StatementResult>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let query = bind_query::<DB>(conn, sql, params).await?;
+  let mut result = StatementResult::default();
+  let mut stream = conn.fetch_many(query);
+  while let Some(item) = stream.try_next().await? {
+    match item {
+      Either::Left(r) => {
+        result.rows_affected += DB::rows_affected(&r);
+        result.last_insert_id = DB::last_insert_id(&r).or(result.last_insert_id);
+      }
+      Either::Right(row) => result.rows.push(DB::row_to_value(row, options)),
+    }
+  }
+  Ok(result)
+}
+
+/// the query with the parameters bound, the parameters should be an array
+pub(crate) async fn bind_query<'q, DB>(
+  conn: &mut DB::Connection,
+  sql: &'q str,
+  params: Option<&'q Value>,
+) -> Result<Query<'q, DB, DB::Arguments<'q>>>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'a> DB::Arguments<'a>: IntoArguments<'a, DB>,
+{
+  Ok(match params {
+    Some(Value::Array(params)) => {
+      let types = if DB::DESCRIBE_PARAMS {
+        param_types::<DB>(conn, sql).await?
+      } else {
+        None
+      };
+      sqlx::query_with::<DB, _>(sql, DB::value_to_args(params, types.as_deref())?)
+    }
+    Some(_) => anyhow::bail!("Unsupported bind parameters, each row should be an array"),
+    None => sqlx::query::<DB>(sql),
+  })
+}
+
+/// the parameter types of the prepared statement, it is cached by the connection
+async fn param_types<DB>(conn: &mut DB::Connection, sql: &str) -> Result<Option<Vec<DB::TypeInfo>>>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+  let statement = conn.prepare(sql).await?;
+  Ok(match statement.parameters() {
+    Some(Either::Left(types)) => Some(types.to_vec()),
+    _ => None,
+  })
+}
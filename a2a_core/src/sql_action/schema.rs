@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use a2a_types::Value;
+use anyhow::Result;
+use serde_json::json;
+use sqlx::{Executor, IntoArguments};
+use tracing::debug;
+
+use super::runner::{query_rows, SqlDatabase};
+
+/// the queries to introspect the database, the columns of the result rows are:
+/// - tables : schema, name, type(table or view), rows, comment
+/// - columns : schema, table, name, type, nullable, default, comment, pk
+/// - foreign_keys : schema, table, name, column, refSchema, refTable, refColumn
+pub(crate) struct SchemaQueries {
+  pub tables: &'static str,
+  pub columns: &'static str,
+  /// empty if the database has no foreign keys
+  pub foreign_keys: &'static str,
+  /// the row estimates of the tables not in the `tables` query: name, rows, empty if none,
+  /// it is optional, eg: `sqlite_stat1` only exists after `ANALYZE`
+  pub row_estimates: &'static str,
+}
+
+/// the tables with columns, primary keys, foreign keys and row count estimates
+pub(crate) async fn schema<DB>(conn: &mut DB::Connection) -> Result<Value>
+where
+  DB: SqlDatabase,
+  for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+  for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+{
+  let queries = &DB::SCHEMA;
+  let tables = query_rows::<DB>(conn, queries.tables).await?;
+  let columns = query_rows::<DB>(conn, queries.columns).await?;
+  let foreign_keys = match queries.foreign_keys {
+    "" => vec![],
+    sql => query_rows::<DB>(conn, sql).await?,
+  };
+  let mut tables = schema_tables(tables, columns, foreign_keys);
+  if !queries.row_estimates.is_empty() {
+    match query_rows::<DB>(conn, queries.row_estimates).await {
+      Ok(rows) => {
+        for row in rows {
+          if let Some(table) = tables.iter_mut().find(|t| t["name"] == row["name"]) {
+            table["rows"] = row["rows"].clone();
+          }
+        }
+      }
+      Err(err) => debug!(%err, "no row estimates"),
+    }
+  }
+  Ok(json!({ "tables": tables }))
+}
+
+/// assemble the tables from the rows of the schema queries
+pub(crate) fn schema_tables(
+  table_rows: Vec<Value>,
+  column_rows: Vec<Value>,
+  foreign_key_rows: Vec<Value>,
+) -> Vec<Value> {
+  let mut tables = BTreeMap::new();
+  for row in table_rows {
+    let mut table = json!({
+      "name": row["name"],
+      "type": row["type"],
+      "columns": [],
+      "primaryKey": [],
+      "foreignKeys": [],
+    });
+    put(&mut table, "schema", &row["schema"]);
+    put(&mut table, "rows", &row["rows"]);
+    put(&mut table, "comment", &row["comment"]);
+    tables.insert(table_key(&row, "name"), table);
+  }
+
+  for row in column_rows {
+    let Some(table) = tables.get_mut(&table_key(&row, "table")) else {
+      continue;
+    };
+    let mut column = json!({
+      "name": row["name"],
+      "type": row["type"],
+      "nullable": is_true(&row["nullable"]),
+    });
+    put(&mut column, "default", &row["default"]);
+    put(&mut column, "comment", &row["comment"]);
+    if is_true(&row["pk"]) {
+      push(table, "primaryKey", row["name"].clone());
+    }
+    push(table, "columns", column);
+  }
+
+  // the columns of the same foreign key are in order
+  for row in foreign_key_rows {
+    let Some(table) = tables.get_mut(&table_key(&row, "table")) else {
+      continue;
+    };
+    let ref_table = match (row["refSchema"].as_str(), row["schema"].as_str()) {
+      (Some(ref_schema), schema) if Some(ref_schema) != schema => {
+        json!(format!(
+          "{}.{}",
+          ref_schema,
+          row["refTable"].as_str().unwrap_or_default()
+        ))
+      }
+      _ => row["refTable"].clone(),
+    };
+    let foreign_keys = table["foreignKeys"].as_array_mut();
+    match foreign_keys.and_then(|f| f.iter_mut().find(|f| f["name"] == row["name"])) {
+      Some(fk) => {
+        push(fk, "columns", row["column"].clone());
+        push(fk, "refColumns", row["refColumn"].clone());
+      }
+      None => push(
+        table,
+        "foreignKeys",
+        json!({
+          "name": row["name"],
+          "columns": [row["column"]],
+          "refTable": ref_table,
+          "refColumns": [row["refColumn"]],
+        }),
+      ),
+    }
+  }
+
+  tables.into_values().collect()
+}
+
+fn table_key(row: &Value, name: &str) -> (String, String) {
+  (
+    row["schema"].as_str().unwrap_or_default().to_string(),
+    row[name].as_str().unwrap_or_default().to_string(),
+  )
+}
+
+/// mysql returns the boolean expression as integer
+fn is_true(v: &Value) -> bool {
+  v.as_bool().unwrap_or_else(|| v.as_i64().unwrap_or(0) != 0)
+}
+
+/// skip the null fields, the schema is put into the prompt
+fn put(obj: &mut Value, key: &str, v: &Value) {
+  if !v.is_null() {
+    obj[key] = v.clone();
+  }
+}
+
+fn push(obj: &mut Value, key: &str, v: Value) {
+  if let Some(a) = obj[key].as_array_mut() {
+    a.push(v);
+  }
+}
@@ -1,122 +1,150 @@
-use a2a_tojson::bytes_to_json;
 use a2a_types::{SqlAction, SqlActionResult, Value};
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use serde_json::json;
 use sqlx::{
-  sqlite::{SqliteArguments, SqliteConnectOptions, SqliteRow},
-  Arguments, Column, Connection, Row, SqliteConnection, TypeInfo,
+  sqlite::{SqliteArguments, SqliteQueryResult, SqliteRow, SqliteTypeInfo},
+  Arguments, Row, Sqlite, TypeInfo, ValueRef,
 };
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
 use tracing::debug;
 
-use super::array_dim;
+use super::{
+  decode::{bytes, get, row_to_object, DecodeOptions},
+  encode::{bind_error, to_bytes, to_datetime, unsupported, TypedParam},
+  pool::sqlite_pool,
+  runner::{run_action, SqlDatabase},
+  schema::SchemaQueries,
+};
 
 pub(crate) async fn do_sql_action(action: SqlAction) -> Result<SqlActionResult> {
-  let options = SqliteConnectOptions::new()
-    .filename(&action.connection)
-    .create_if_missing(true);
-
-  let mut conn = SqliteConnection::connect_with(&options).await?;
-  let sql = &action.query;
-  match array_dim(action.rows.as_ref()) {
-    0 => {
-      // no bind parameters
-      let rows = sqlx::query(sql).fetch_all(&mut conn).await?;
-      let mut result = Vec::new();
-      for row in rows {
-        result.push(row_to_value(row));
-      }
-      Ok(SqlActionResult::Array(result))
-    }
-    1 => {
-      if let Some(args) = value_to_args(action.rows.as_ref()) {
-        let rows = sqlx::query_with(sql, args).fetch_all(&mut conn).await?;
-        let mut result = Vec::new();
-        for row in rows {
-          result.push(row_to_value(row));
-        }
-        Ok(SqlActionResult::Array(result))
-      } else {
-        anyhow::bail!("Unsupported bind parameters");
-      }
-    }
-    2 => {
-      if let Some(Value::Array(a)) = action.rows {
-        for row in a {
-          if let Some(args) = value_to_args(Some(&row)) {
-            sqlx::query_with(sql, args).execute(&mut conn).await?;
-          }
-        }
-      }
-      Ok(SqlActionResult::Array(Vec::new()))
-    }
-    _ => anyhow::bail!("Unsupported rows value"),
+  let mut conn = sqlite_pool(&action.connection)?.acquire().await?;
+  run_action::<Sqlite>(&mut conn, &action).await
+}
+
+impl SqlDatabase for Sqlite {
+  const SCHEMA: SchemaQueries = SchemaQueries {
+    tables: SCHEMA_TABLES,
+    columns: SCHEMA_COLUMNS,
+    foreign_keys: SCHEMA_FOREIGN_KEYS,
+    row_estimates: SCHEMA_ROW_ESTIMATES,
+  };
+
+  fn row_to_value(row: SqliteRow, options: &DecodeOptions) -> Value {
+    row_to_value(row, options)
+  }
+
+  fn value_to_args<'q>(
+    values: &'q [Value],
+    _types: Option<&[SqliteTypeInfo]>,
+  ) -> Result<SqliteArguments<'q>> {
+    value_to_args(values)
+  }
+
+  fn rows_affected(result: &SqliteQueryResult) -> u64 {
+    result.rows_affected()
+  }
+
+  fn last_insert_id(result: &SqliteQueryResult) -> Option<i64> {
+    Some(result.last_insert_rowid()).filter(|id| *id > 0)
   }
 }
 
-fn row_to_value(row: SqliteRow) -> Value {
-  let mut val = serde_json::Map::new();
-  row.columns().iter().enumerate().for_each(|(i, col)| {
-    debug!(
-      col_name = col.name(),
-      col_type = col.type_info().name(),
-      "Processing column"
-    );
-    let value = match col.type_info().name() {
-      "BOOLEAN" => Value::Bool(row.get(i)),
-      "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" => Value::String(row.get(i)),
-      "INTEGER" | "INT4" | "INT8" => {
-        let n: i64 = row.get(i);
-        json!(n)
-      }
-      "REAL" => {
-        let n: f64 = row.get(i);
-        json!(n)
-      }
-      "BLOB" => {
-        let data: Vec<u8> = row.get(i);
-        bytes_to_json(bytes::Bytes::from(data), "", None).unwrap_or(Value::Null)
-      }
-      _ => {
-        if let Ok(s) = row.try_get::<String, usize>(i) {
-          Value::String(s)
-        } else if let Ok(n) = row.try_get::<i64, usize>(i) {
-          json!(n)
-        } else if let Ok(n) = row.try_get::<f64, usize>(i) {
-          json!(n)
-        } else if let Ok(b) = row.try_get::<bool, usize>(i) {
-          Value::Bool(b)
-        } else if let Ok(data) = row.try_get::<Vec<u8>, usize>(i) {
-          bytes_to_json(bytes::Bytes::from(data), "", None).unwrap_or(Value::Null)
-        } else {
-          Value::Null
-        }
-      }
-    };
-    val.insert(col.name().to_string(), value);
-  });
-  Value::Object(val)
+fn row_to_value(row: SqliteRow, options: &DecodeOptions) -> Value {
+  row_to_object(&row, |row, i, type_info| decode(row, i, type_info, options))
 }
 
-fn value_to_args(val: Option<&Value>) -> Option<SqliteArguments<'_>> {
-  match val {
-    Some(Value::Array(a)) => {
-      let mut args = SqliteArguments::default();
-      for v in a {
-        let _ = match v {
-          Value::Bool(b) => args.add(*b),
-          Value::String(s) => args.add(s.as_str()),
-          Value::Number(n) => {
-            if n.is_f64() {
-              args.add(n.as_f64().unwrap_or_default())
-            } else {
-              args.add(n.as_i64().unwrap_or_default())
-            }
-          }
-          _ => Ok(()),
-        };
-      }
-      Some(args)
-    }
-    _ => None,
+/// the type of sqlite is the declared type of the column, the value can be any type actually
+fn decode(
+  row: &SqliteRow,
+  i: usize,
+  type_info: &SqliteTypeInfo,
+  options: &DecodeOptions,
+) -> Option<Value> {
+  debug!(column = i, col_type = type_info.name(), "Processing column");
+  let value = match type_info.name() {
+    "BOOLEAN" => json!(get::<bool, _>(row, i)?),
+    "TEXT" => json!(get::<String, _>(row, i)?),
+    "INTEGER" => json!(get::<i64, _>(row, i)?),
+    "REAL" => json!(get::<f64, _>(row, i)?),
+    "BLOB" => bytes(get(row, i)?),
+    "DATETIME" => match row.try_get_unchecked::<OffsetDateTime, _>(i) {
+      Ok(dt) => options.datetime(dt),
+      Err(_) => match row.try_get_unchecked::<PrimitiveDateTime, _>(i) {
+        Ok(dt) => options.primitive_datetime(dt),
+        Err(_) => dynamic(row, i)?,
+      },
+    },
+    _ => dynamic(row, i)?,
+  };
+  Some(value)
+}
+
+/// the value of the storage class, for the columns declared as NUMERIC, DATE or no type
+fn dynamic(row: &SqliteRow, i: usize) -> Option<Value> {
+  let value = match row.try_get_raw(i).ok()?.type_info().name() {
+    "INTEGER" => json!(get::<i64, _>(row, i)?),
+    "REAL" => json!(get::<f64, _>(row, i)?),
+    "BLOB" => bytes(get(row, i)?),
+    _ => json!(get::<String, _>(row, i)?),
+  };
+  Some(value)
+}
+
+/// the values are bound by their json type, sqlite has no datetime type, the typed datetime is
+/// bound as the ISO 8601 string in utc
+fn value_to_args(values: &[Value]) -> Result<SqliteArguments<'_>> {
+  let mut args = SqliteArguments::default();
+  for (i, v) in values.iter().enumerate() {
+    bind(&mut args, v).map_err(|err| bind_error(i, err))?;
   }
+  Ok(args)
+}
+
+fn bind<'q>(args: &mut SqliteArguments<'q>, v: &'q Value) -> Result<()> {
+  let result = match (v, TypedParam::from_value(v)) {
+    (_, Some(TypedParam::Datetime(d))) => args.add(
+      to_datetime(d)
+        .ok_or_else(|| unsupported(d, "DATETIME"))?
+        .to_offset(UtcOffset::UTC),
+    ),
+    (_, Some(TypedParam::Blob(b))) => args.add(to_bytes(b).ok_or_else(|| unsupported(b, "BLOB"))?),
+    (Value::Null, _) => args.add(None::<String>),
+    (Value::Bool(b), _) => args.add(*b),
+    (Value::Number(n), _) if n.is_f64() => args.add(n.as_f64().unwrap_or_default()),
+    (Value::Number(n), _) => args.add(n.as_i64().ok_or_else(|| unsupported(v, "INTEGER"))?),
+    (Value::String(s), _) => args.add(s.as_str()),
+    (Value::Object(_) | Value::Array(_), _) => args.add(v.to_string()),
+  };
+  result.map_err(|err| anyhow!(err))
 }
+
+/// sqlite has no row estimate before `ANALYZE`, the rows are null instead of counting every table
+const SCHEMA_TABLES: &str = r#"
+SELECT NULL AS schema, name, type, NULL AS "rows", NULL AS comment
+FROM sqlite_master
+WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%'
+ORDER BY name
+"#;
+
+/// the first number of the stat is the rows of the table, the table without index has a NULL idx
+const SCHEMA_ROW_ESTIMATES: &str = r#"
+SELECT tbl AS name, max(CAST(substr(stat, 1, instr(stat || ' ', ' ') - 1) AS INTEGER)) AS "rows"
+FROM sqlite_stat1
+GROUP BY tbl
+"#;
+
+const SCHEMA_COLUMNS: &str = r#"
+SELECT NULL AS schema, m.name AS "table", c.name, c.type, NOT c."notnull" AS nullable,
+  c.dflt_value AS "default", NULL AS comment, c.pk > 0 AS pk
+FROM sqlite_master m JOIN pragma_table_info(m.name) c
+WHERE m.type IN ('table', 'view') AND m.name NOT LIKE 'sqlite_%'
+ORDER BY m.name, c.cid
+"#;
+
+const SCHEMA_FOREIGN_KEYS: &str = r#"
+SELECT NULL AS schema, m.name AS "table", 'fk_' || f.id AS name, f."from" AS "column",
+  NULL AS refSchema, f."table" AS refTable, f."to" AS refColumn
+FROM sqlite_master m JOIN pragma_foreign_key_list(m.name) f
+WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+ORDER BY m.name, f.id, f.seq
+"#;
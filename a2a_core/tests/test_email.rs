@@ -0,0 +1,333 @@
+use a2a_core::{do_action, utils::uuid_v7};
+use a2a_types::{Action, EMailAction, FileAction, Value};
+use rustls::crypto::{aws_lc_rs, CryptoProvider};
+use serde_json::json;
+
+/// the sent mail of the smtp sink, the envelope sender, the recipients and the data
+#[derive(Debug, Default)]
+struct SentMail {
+  from: String,
+  recipients: Vec<String>,
+  data: String,
+}
+
+/// a local smtp sink without authentication, the mails are sent back by the channel
+async fn smtp_sink() -> (u16, tokio::sync::mpsc::UnboundedReceiver<SentMail>) {
+  use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+  let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+  tokio::spawn(async move {
+    while let Ok((socket, _)) = listener.accept().await {
+      let tx = tx.clone();
+      tokio::spawn(async move {
+        let (read, mut write) = socket.into_split();
+        let mut lines = BufReader::new(read).lines();
+        write.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+        let mut mail = SentMail::default();
+        while let Ok(Some(line)) = lines.next_line().await {
+          let command = line.to_uppercase();
+          let reply = if command.starts_with("EHLO") || command.starts_with("HELO") {
+            "250-sink\r\n250 8BITMIME\r\n"
+          } else if command.starts_with("MAIL FROM:") {
+            mail.from = line[10..].trim_matches(['<', '>', ' ']).to_string();
+            "250 OK\r\n"
+          } else if command.starts_with("RCPT TO:") {
+            mail
+              .recipients
+              .push(line[8..].trim_matches(['<', '>', ' ']).to_string());
+            "250 OK\r\n"
+          } else if command == "DATA" {
+            write.write_all(b"354 go ahead\r\n").await.unwrap();
+            while let Ok(Some(line)) = lines.next_line().await {
+              if line == "." {
+                break;
+              }
+              mail.data.push_str(&line);
+              mail.data.push('\n');
+            }
+            tx.send(std::mem::take(&mut mail)).ok();
+            "250 OK\r\n"
+          } else if command == "QUIT" {
+            write.write_all(b"221 bye\r\n").await.ok();
+            break;
+          } else {
+            "250 OK\r\n"
+          };
+          write.write_all(reply.as_bytes()).await.unwrap();
+        }
+      });
+    }
+  });
+  (port, rx)
+}
+
+fn smtp_account(port: u16) -> Value {
+  json!({
+    "smtp": {
+      "host": "127.0.0.1",
+      "port": port,
+      "encryption": {"type": "none"},
+      "login": "me@example.com",
+    }
+  })
+}
+
+#[tokio::test]
+async fn test_email_send() {
+  CryptoProvider::install_default(aws_lc_rs::default_provider()).ok();
+  let (port, mut sent) = smtp_sink().await;
+  let file = std::env::temp_dir().join(format!("{}.csv", uuid_v7()));
+  std::fs::write(&file, "a,b\n1,2\n").unwrap();
+
+  let action = EMailAction {
+    method: "SEND".to_string(),
+    account: smtp_account(port),
+    message: Some(json!({
+      "from": "Me <me@example.com>",
+      "to": "Tom <tom@example.com>, ann@example.com",
+      "cc": [{"name": "Bob", "address": "bob@example.com"}],
+      "bcc": "boss@example.com",
+      "subject": "weekly report",
+      "text": "see the attachments",
+      "html": "<p>see the attachments</p>",
+      "replyTo": "team@example.com",
+      "inReplyTo": "<report-1@example.com>",
+      "attachments": [
+        file.to_str().unwrap(),
+        {"path": "data:text/plain;base64,aGVsbG8=", "filename": "hello.txt"},
+      ],
+    })),
+    ..Default::default()
+  };
+  let result = do_action(Action::EMail(action)).await.unwrap();
+  assert_eq!(
+    result["recipients"],
+    json!([
+      "tom@example.com",
+      "ann@example.com",
+      "bob@example.com",
+      "boss@example.com"
+    ])
+  );
+
+  let mail = sent.recv().await.unwrap();
+  assert_eq!(mail.from, "me@example.com");
+  assert_eq!(mail.recipients.len(), 4);
+  assert!(mail.recipients.contains(&"boss@example.com".to_string()));
+  let data = mail.data;
+  let message_id = result["messageId"].as_str().unwrap();
+  assert!(data.contains(&format!("Message-ID: <{}>", message_id)));
+  assert!(data.contains("Subject: weekly report"));
+  assert!(data.contains("In-Reply-To: <report-1@example.com>"));
+  assert!(data.contains("References: <report-1@example.com>"));
+  assert!(data.contains("Reply-To: <team@example.com>"));
+  assert!(data.contains("\"Bob\" <bob@example.com>"));
+  // the bcc recipients are only in the envelope
+  assert!(!data.to_lowercase().contains("boss@example.com"));
+  assert!(data.contains("text/html"));
+  assert!(data.contains(file.file_name().unwrap().to_str().unwrap()));
+  assert!(data.contains("hello.txt"));
+  std::fs::remove_file(file).ok();
+
+  // the recipients are required
+  let action = EMailAction {
+    method: "SEND".to_string(),
+    account: smtp_account(port),
+    message: Some(json!({"subject": "nobody"})),
+    ..Default::default()
+  };
+  let err = do_action(Action::EMail(action)).await.unwrap_err();
+  assert!(err.to_string().contains("recipients"), "{}", err);
+}
+
+/// a raw message with an optional attachment
+fn raw_mail(subject: &str, from: &str, date: &str, attachment: Option<&str>) -> String {
+  let mut raw = format!(
+    "From: {}\r\nTo: me@example.com\r\nSubject: {}\r\nDate: {}\r\nMessage-ID: <{}@example.com>\r\n",
+    from,
+    subject,
+    date,
+    subject.replace(' ', "-")
+  );
+  match attachment {
+    Some(filename) => raw.push_str(&format!(
+      concat!(
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n\r\n",
+        "--b\r\nContent-Type: text/plain\r\n\r\n{} body\r\n",
+        "--b\r\nContent-Type: text/plain; name=\"{}\"\r\n",
+        "Content-Disposition: attachment; filename=\"{}\"\r\n\r\nhello\r\n--b--\r\n"
+      ),
+      subject, filename, filename
+    )),
+    None => raw.push_str(&format!("\r\n{} body\r\n", subject)),
+  }
+  raw
+}
+
+async fn read_source(source: &str, filter: Option<Value>, last_id: Option<u64>) -> Value {
+  let action = EMailAction {
+    method: "READ".to_string(),
+    source: Some(source.to_string()),
+    filter,
+    last_id,
+    attachment_dir: Some(
+      std::env::temp_dir()
+        .join("a2a_test")
+        .join(uuid_v7())
+        .to_string_lossy()
+        .to_string(),
+    ),
+    ..Default::default()
+  };
+  do_action(Action::EMail(action)).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_email_read_mbox() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(&base).unwrap();
+  let mbox = base.join("archive.mbox");
+  let mails = [
+    raw_mail(
+      "invoice 1",
+      "Supplier <supplier@x.com>",
+      "Mon, 4 Mar 2024 10:00:00 +0000",
+      Some("../../a.txt"),
+    ),
+    raw_mail("hello", "tom@y.com", "Tue, 5 Mar 2024 10:00:00 +0000", None),
+    raw_mail(
+      "invoice 2",
+      "supplier@x.com",
+      "Wed, 6 Mar 2024 10:00:00 +0000",
+      Some("a.txt"),
+    ),
+  ];
+  let content = mails
+    .iter()
+    .enumerate()
+    .map(|(i, m)| {
+      let status = if i == 0 { "Status: RO\r\n" } else { "" };
+      format!(
+        "From sender@x.com Mon Mar  4 10:00:00 2024\r\n{}{}\r\n",
+        status, m
+      )
+    })
+    .collect::<String>();
+  std::fs::write(&mbox, content).unwrap();
+  let source = mbox.to_str().unwrap();
+
+  // the newest first by default
+  let result = read_source(source, None, None).await;
+  let subjects = result
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|m| m["subject"].as_str().unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(subjects, vec!["invoice 2", "hello", "invoice 1"]);
+  assert_eq!(result[2]["id"], "1");
+  assert_eq!(result[2]["flags"], json!(["seen"]));
+  assert_eq!(result[2]["from"]["name"], "Supplier");
+  let path = result[2]["attachments"][0]["path"].as_str().unwrap();
+  assert!(path.ends_with("archive.mbox/INBOX/1/a.txt"), "{}", path);
+  assert_eq!(std::fs::read_to_string(path).unwrap().trim(), "hello");
+
+  let result = read_source(
+    source,
+    Some(json!({"from": "supplier@x.com", "unseen": true, "hasAttachment": true})),
+    None,
+  )
+  .await;
+  assert_eq!(result.as_array().unwrap().len(), 1);
+  assert_eq!(result[0]["subject"], "invoice 2");
+
+  let result = read_source(source, None, Some(2)).await;
+  assert_eq!(result.as_array().unwrap().len(), 1);
+  assert_eq!(result[0]["id"], "3");
+  // the `last_id` of the older scripts
+  for action in [
+    json!({"method": "READ", "account": {}, "lastId": 2}),
+    json!({"method": "READ", "account": {}, "last_id": 2}),
+  ] {
+    let action = serde_json::from_value::<EMailAction>(action).unwrap();
+    assert_eq!(action.last_id, Some(2));
+  }
+  std::fs::remove_dir_all(base).ok();
+}
+
+#[tokio::test]
+async fn test_email_read_maildir() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  for dir in ["cur", "new", "tmp"] {
+    std::fs::create_dir_all(base.join(dir)).unwrap();
+  }
+  std::fs::write(
+    base.join("cur/1709546400.1.host:2,FS"),
+    raw_mail("first", "a@x.com", "Mon, 4 Mar 2024 10:00:00 +0000", None),
+  )
+  .unwrap();
+  std::fs::write(
+    base.join("new/1709632800.2.host"),
+    raw_mail("second", "b@x.com", "Tue, 5 Mar 2024 10:00:00 +0000", None),
+  )
+  .unwrap();
+
+  let result = read_source(base.to_str().unwrap(), Some(json!({"flagged": true})), None).await;
+  assert_eq!(result.as_array().unwrap().len(), 1);
+  assert_eq!(result[0]["subject"], "first");
+  assert_eq!(result[0]["flags"], json!(["flagged", "seen"]));
+
+  let result = read_source(base.to_str().unwrap(), Some(json!({"unseen": true})), None).await;
+  assert_eq!(result[0]["subject"], "second");
+  // the number in the delivered order
+  assert_eq!(result[0]["id"], "2");
+  assert_eq!(result[0]["body"].as_str().unwrap().trim(), "second body");
+
+  // the source only supports READ
+  let action = EMailAction {
+    method: "DELETE".to_string(),
+    source: Some(base.to_string_lossy().to_string()),
+    ids: Some(json!([1])),
+    ..Default::default()
+  };
+  assert!(do_action(Action::EMail(action)).await.is_err());
+  std::fs::remove_dir_all(base).ok();
+}
+
+#[tokio::test]
+async fn test_file_read_eml() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(&base).unwrap();
+  let eml = base.join("invoice.eml");
+  std::fs::write(
+    &eml,
+    raw_mail(
+      "invoice",
+      "Supplier <supplier@x.com>",
+      "Mon, 4 Mar 2024 10:00:00 +0000",
+      Some("a.txt"),
+    ),
+  )
+  .unwrap();
+
+  let action = FileAction {
+    method: "READ".to_string(),
+    path: eml.to_string_lossy().to_string(),
+    options: Some(json!({"attachmentDir": base.join("attachments")})),
+    ..Default::default()
+  };
+  let result = do_action(Action::File(action)).await.unwrap();
+  assert_eq!(result["id"], "1");
+  assert_eq!(result["flags"], json!([]));
+  assert_eq!(result["subject"], "invoice");
+  assert_eq!(result["messageId"], "invoice@example.com");
+  assert_eq!(result["from"]["address"], "supplier@x.com");
+  assert_eq!(result["date"], "2024-03-04T10:00:00Z");
+  assert_eq!(result["attachments"][0]["filename"], "a.txt");
+  let path = result["attachments"][0]["path"].as_str().unwrap();
+  assert!(path.ends_with("invoice.eml/INBOX/1/a.txt"), "{}", path);
+  assert_eq!(std::fs::read_to_string(path).unwrap().trim(), "hello");
+  std::fs::remove_dir_all(base).ok();
+}
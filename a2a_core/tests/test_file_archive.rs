@@ -0,0 +1,100 @@
+use a2a_core::{do_action, utils::uuid_v7};
+use a2a_types::{Action, FileAction, Value};
+use serde_json::json;
+
+async fn file_action(
+  method: &str,
+  path: String,
+  body: Option<Value>,
+  options: Option<Value>,
+) -> Value {
+  let action = FileAction {
+    method: method.to_string(),
+    path,
+    body,
+    options,
+    ..Default::default()
+  };
+  do_action(Action::File(action)).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_archive() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(base.join("src/data")).unwrap();
+  std::fs::write(base.join("src/data/a.csv"), "name,age\nalice,20\n").unwrap();
+  std::fs::write(base.join("src/data/b.txt"), "hello").unwrap();
+  let base = base.to_str().unwrap();
+
+  for ext in ["zip", "tar.gz", "tar.zst", "7z"] {
+    let archive = format!("{}/bundle.{}", base, ext);
+    let result = file_action(
+      "ARCHIVE",
+      archive.clone(),
+      Some(json!([
+        format!("{}/src/**/*", base),
+        {"name": "meta.json", "content": {"version": 1}},
+      ])),
+      None,
+    )
+    .await;
+    assert_eq!(result["files"].as_array().unwrap().len(), 3, "{}", ext);
+
+    let scheme = if ext == "7z" {
+      "7z"
+    } else {
+      ext.split('.').next().unwrap()
+    };
+    let entries = file_action("LIST", format!("{}://{}", scheme, archive), None, None).await;
+    let mut names = entries
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|e| e["path"].as_str().unwrap().to_string())
+      .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(
+      names,
+      vec!["data/a.csv", "data/b.txt", "meta.json"],
+      "{}",
+      ext
+    );
+
+    let rows = file_action(
+      "READ",
+      format!("{}://{}!/data/a.csv", scheme, archive),
+      None,
+      None,
+    )
+    .await;
+    assert_eq!(rows, json!([{"name": "alice", "age": 20}]), "{}", ext);
+
+    let meta = file_action(
+      "READ",
+      format!("{}://{}!/meta.json", scheme, archive),
+      None,
+      None,
+    )
+    .await;
+    assert_eq!(meta, json!({"version": 1}), "{}", ext);
+
+    let dest = format!("{}/out_{}", base, ext.replace('.', "_"));
+    let extracted = file_action(
+      "EXTRACT",
+      archive.clone(),
+      None,
+      Some(json!({"dest": dest, "files": ["data/*.csv"]})),
+    )
+    .await;
+    assert_eq!(
+      extracted,
+      json!([format!("{}/data/a.csv", dest)]),
+      "{}",
+      ext
+    );
+    assert_eq!(
+      std::fs::read_to_string(format!("{}/data/a.csv", dest)).unwrap(),
+      "name,age\nalice,20\n"
+    );
+  }
+}
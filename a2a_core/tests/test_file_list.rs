@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+use a2a_core::{do_action, utils::uuid_v7};
+use a2a_types::{Action, FileAction, Value};
+use serde_json::json;
+
+fn setup_files() -> PathBuf {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  let files = [
+    ("reports/a.csv", "a,b\n1,2\n"),
+    ("reports/b.csv", "a,b\n1,2\n3,4\n"),
+    ("reports/c.txt", "c"),
+    ("data/2024-01-01.json", "{}"),
+    ("data/2024-02-03.json", "{}"),
+    ("data/2023-12-31.json", "{}"),
+    ("data/sub/x.json", "{}"),
+  ];
+  for (name, content) in files {
+    let file = base.join(name);
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    std::fs::write(file, content).unwrap();
+  }
+  base
+}
+
+async fn list(path: String, options: Option<Value>) -> Vec<String> {
+  let action = FileAction {
+    method: "LIST".to_string(),
+    path,
+    options,
+    ..Default::default()
+  };
+  let result = do_action(Action::File(action)).await.unwrap();
+  result
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|item| item["name"].as_str().unwrap().to_string())
+    .collect()
+}
+
+fn sorted(mut names: Vec<String>) -> Vec<String> {
+  names.sort();
+  names
+}
+
+#[tokio::test]
+async fn test_list_glob() {
+  let base = setup_files();
+  let base = base.to_str().unwrap();
+
+  let names = list(format!("{}/reports/*.csv", base), None).await;
+  assert_eq!(sorted(names), vec!["a.csv", "b.csv"]);
+
+  let names = list(format!("{}/data/2024-??-*.json", base), None).await;
+  assert_eq!(sorted(names), vec!["2024-01-01.json", "2024-02-03.json"]);
+
+  let names = list(format!("{}/reports/[ab].{{csv,txt}}", base), None).await;
+  assert_eq!(sorted(names), vec!["a.csv", "b.csv"]);
+
+  let names = list(format!("{}/reports/*.{{csv,txt}}", base), None).await;
+  assert_eq!(sorted(names), vec!["a.csv", "b.csv", "c.txt"]);
+
+  // single segment pattern doesn't walk into sub directories
+  let names = list(format!("{}/data/*.json", base), None).await;
+  assert_eq!(names.len(), 3);
+
+  let names = list(format!("{}/**/*.json", base), None).await;
+  assert_eq!(
+    sorted(names),
+    vec![
+      "2023-12-31.json",
+      "2024-01-01.json",
+      "2024-02-03.json",
+      "x.json"
+    ]
+  );
+
+  let names = list(format!("{}/*/sub/*.json", base), None).await;
+  assert_eq!(names, vec!["x.json"]);
+}
+
+#[tokio::test]
+async fn test_list_options() {
+  let base = setup_files();
+  let base = base.to_str().unwrap();
+
+  let names = list(
+    format!("{}/reports/*", base),
+    Some(json!({"sortBy": "size", "sortDesc": true, "limit": 2})),
+  )
+  .await;
+  assert_eq!(names, vec!["b.csv", "a.csv"]);
+
+  let names = list(format!("{}/reports/*", base), Some(json!({"minSize": 2}))).await;
+  assert_eq!(sorted(names), vec!["a.csv", "b.csv"]);
+
+  let names = list(
+    format!("{}/reports/*", base),
+    Some(json!({"modifiedAfter": "2000-01-01", "maxSize": 1})),
+  )
+  .await;
+  assert_eq!(names, vec!["c.txt"]);
+
+  let names = list(
+    format!("{}/reports/*", base),
+    Some(json!({"modifiedBefore": "2000-01-01T00:00:00Z"})),
+  )
+  .await;
+  assert!(names.is_empty());
+}
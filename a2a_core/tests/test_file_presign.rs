@@ -0,0 +1,85 @@
+use a2a_core::{do_action, init_file_link, utils::uuid_v7, verify_file_link};
+use a2a_types::{Action, FileAction, Value};
+use serde_json::json;
+
+async fn presign(path: String, options: Value, connection: Option<Value>) -> anyhow::Result<Value> {
+  let action = FileAction {
+    method: "PRESIGN".to_string(),
+    path,
+    options: Some(options),
+    connection,
+    ..Default::default()
+  };
+  do_action(Action::File(action)).await
+}
+
+#[tokio::test]
+async fn test_presign_s3() {
+  let connection = json!({
+    "bucket": "reports",
+    "region": "us-east-1",
+    "endpoint": "http://127.0.0.1:9000",
+    "access_key_id": "minio",
+    "secret_access_key": "minio123",
+  });
+  let result = presign(
+    "s3://2024/a.csv".to_string(),
+    json!({"expires": 600}),
+    Some(connection.clone()),
+  )
+  .await
+  .unwrap();
+  let url = result["url"].as_str().unwrap();
+  assert!(url.contains("X-Amz-Signature="), "{}", url);
+  assert!(url.contains("X-Amz-Expires=600"), "{}", url);
+  assert_eq!(result["method"], "GET");
+
+  let result = presign(
+    "s3://2024/b.csv".to_string(),
+    json!({"method": "write", "contentType": "text/csv"}),
+    Some(connection),
+  )
+  .await
+  .unwrap();
+  assert_eq!(result["method"], "PUT");
+}
+
+#[tokio::test]
+async fn test_presign_local() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(&base).unwrap();
+  let file = base.join("a b.csv");
+  std::fs::write(&file, "a,b\n").unwrap();
+  let file = file.to_str().unwrap().to_string();
+
+  init_file_link("http://localhost:30030/", "secret");
+  let result = presign(file.clone(), json!({"expires": 60}), None)
+    .await
+    .unwrap();
+  let url = result["url"].as_str().unwrap();
+  assert!(url.starts_with("http://localhost:30030/files/"), "{}", url);
+  assert!(url.contains("a%20b.csv?expires="), "{}", url);
+
+  let (link, query) = url
+    .trim_start_matches("http://localhost:30030/files/")
+    .split_once('?')
+    .unwrap();
+  let link = urlencoding::decode(link).unwrap();
+  let mut expires = 0;
+  let mut sig = "";
+  for kv in query.split('&') {
+    match kv.split_once('=').unwrap() {
+      ("expires", v) => expires = v.parse().unwrap(),
+      ("sig", v) => sig = v,
+      _ => {}
+    }
+  }
+  let path = verify_file_link(&link, expires, sig).unwrap();
+  assert_eq!(std::fs::read_to_string(path).unwrap(), "a,b\n");
+  assert!(verify_file_link(&link, expires + 1, sig).is_none());
+  assert!(verify_file_link(&link, 1, sig).is_none());
+
+  assert!(presign(file, json!({"method": "write"}), None)
+    .await
+    .is_err());
+}
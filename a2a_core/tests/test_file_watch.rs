@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use a2a_core::{do_action, utils::uuid_v7};
+use a2a_types::{Action, FileAction, Value};
+use serde_json::json;
+
+async fn watch(path: String, options: Value) -> Value {
+  let action = FileAction {
+    method: "WATCH".to_string(),
+    path,
+    options: Some(options),
+    ..Default::default()
+  };
+  do_action(Action::File(action)).await.unwrap()
+}
+
+fn names(result: &Value, kind: &str) -> Vec<String> {
+  let mut names = result[kind]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|item| item["name"].as_str().unwrap().to_string())
+    .collect::<Vec<_>>();
+  names.sort();
+  names
+}
+
+fn write(base: &Path, name: &str, content: &str) {
+  std::fs::write(base.join(name), content).unwrap();
+}
+
+#[tokio::test]
+async fn test_watch_diff() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(base.join("inbox")).unwrap();
+  let inbox = base.join("inbox");
+  write(&inbox, "a.csv", "a");
+  write(&inbox, "b.csv", "b");
+  write(&inbox, "c.txt", "c");
+
+  let path = format!("{}/*.csv", inbox.to_str().unwrap());
+  let options = json!({"state": base.join("state.json")});
+
+  let result = watch(path.clone(), options.clone()).await;
+  assert_eq!(names(&result, "created"), vec!["a.csv", "b.csv"]);
+  assert!(result["since"].is_null());
+
+  let result = watch(path.clone(), options.clone()).await;
+  assert!(names(&result, "created").is_empty());
+  assert!(names(&result, "modified").is_empty());
+  assert!(names(&result, "deleted").is_empty());
+  assert!(result["since"].is_number());
+
+  write(&inbox, "a.csv", "a changed");
+  write(&inbox, "d.csv", "d");
+  std::fs::remove_file(inbox.join("b.csv")).unwrap();
+  let result = watch(path.clone(), options).await;
+  assert_eq!(names(&result, "created"), vec!["d.csv"]);
+  assert_eq!(names(&result, "modified"), vec!["a.csv"]);
+  assert_eq!(names(&result, "deleted"), vec!["b.csv"]);
+}
+
+#[tokio::test]
+async fn test_watch_wait() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(base.join("inbox")).unwrap();
+  let inbox = base.join("inbox");
+  write(&inbox, "old.json", "{}");
+
+  let path = format!("{}/", inbox.to_str().unwrap());
+  let options = json!({"state": base.join("state.json"), "skipExisting": true, "wait": 10});
+
+  let writer = tokio::spawn(async move {
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    write(&inbox, "new.json", "{}");
+  });
+  let start = std::time::Instant::now();
+  let result = watch(path, options).await;
+  writer.await.unwrap();
+  assert_eq!(names(&result, "created"), vec!["new.json"]);
+  assert!(start.elapsed() < std::time::Duration::from_secs(5));
+}
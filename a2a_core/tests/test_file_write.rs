@@ -0,0 +1,123 @@
+use a2a_core::{do_action, utils::uuid_v7};
+use a2a_types::{Action, FileAction, Value};
+use serde_json::json;
+
+async fn file_action(
+  method: &str,
+  path: &str,
+  body: Option<Value>,
+  options: Value,
+) -> anyhow::Result<Value> {
+  let action = FileAction {
+    method: method.to_string(),
+    path: path.to_string(),
+    body,
+    options: Some(options),
+    ..Default::default()
+  };
+  do_action(Action::File(action)).await
+}
+
+#[tokio::test]
+async fn test_conditional_write() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(&base).unwrap();
+  let path = base.join("out.txt");
+  let path = path.to_str().unwrap();
+
+  let first = file_action(
+    "WRITE",
+    path,
+    Some(json!("v1")),
+    json!({"ifNotExists": true}),
+  )
+  .await
+  .unwrap();
+  assert_eq!(first["size"], 2);
+
+  let err = file_action(
+    "WRITE",
+    path,
+    Some(json!("v2")),
+    json!({"ifNotExists": true}),
+  )
+  .await
+  .unwrap_err();
+  assert!(err.to_string().contains("Write conflict"), "{}", err);
+
+  let etag = first["eTag"].as_str().unwrap();
+  let second = file_action("WRITE", path, Some(json!("v2")), json!({"ifMatch": etag}))
+    .await
+    .unwrap();
+  assert_ne!(second["eTag"], first["eTag"]);
+
+  // LIST gives the same eTag as WRITE for the local file
+  let pattern = format!("{}/*.txt", base.to_str().unwrap());
+  let listed = file_action("LIST", &pattern, None, json!({"eTag": true}))
+    .await
+    .unwrap();
+  assert_eq!(listed[0]["eTag"], second["eTag"]);
+  let listed = file_action("LIST", &pattern, None, json!({}))
+    .await
+    .unwrap();
+  assert_eq!(listed[0]["eTag"], Value::Null);
+
+  // the stale eTag is rejected
+  let err = file_action("WRITE", path, Some(json!("v3")), json!({"ifMatch": etag}))
+    .await
+    .unwrap_err();
+  assert!(err.to_string().contains("Write conflict"), "{}", err);
+  assert_eq!(std::fs::read_to_string(path).unwrap(), "v2");
+}
+
+#[tokio::test]
+async fn test_checksum() {
+  let base = std::env::temp_dir().join("a2a_test").join(uuid_v7());
+  std::fs::create_dir_all(&base).unwrap();
+  let path = base.join("hello.txt");
+  let path = path.to_str().unwrap();
+
+  let result = file_action(
+    "WRITE",
+    path,
+    Some(json!("hello")),
+    json!({"checksum": "sha256"}),
+  )
+  .await
+  .unwrap();
+  assert_eq!(
+    result["checksum"],
+    "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+  );
+
+  let content = file_action(
+    "READ",
+    path,
+    None,
+    json!({"expectedChecksum": "5d41402abc4b2a76b9719d911017c592"}),
+  )
+  .await
+  .unwrap();
+  assert_eq!(content, "hello");
+
+  // base64 of the md5
+  let content = file_action(
+    "READ",
+    path,
+    None,
+    json!({"checksum": "md5", "expectedChecksum": "XUFAKrxLKna5cZ2REBfFkg=="}),
+  )
+  .await
+  .unwrap();
+  assert_eq!(content, "hello");
+
+  let err = file_action(
+    "READ",
+    path,
+    None,
+    json!({"checksum": "sha256", "expectedChecksum": "00"}),
+  )
+  .await
+  .unwrap_err();
+  assert!(err.to_string().contains("Checksum mismatch"), "{}", err);
+}
@@ -1,10 +1,25 @@
 use a2a_types::Value;
 use anyhow::Result;
-use calamine::{Data, Range, Reader};
+use calamine::{Data, Range, Reader, Sheets};
 
 use crate::json2excel::{is_plain_2d, json_to_excel};
 
 pub(crate) fn to_json(input: String, options: Option<&Value>) -> Result<Value> {
+  let workbook = calamine::open_workbook_auto(input.clone()).map_err(|err| anyhow::anyhow!(err))?;
+  workbook_to_json(workbook, options)
+}
+
+/// the workbook in the bytes, eg: the content of a xlsx file
+pub(crate) fn bytes_to_json(input: bytes::Bytes, options: Option<&Value>) -> Result<Value> {
+  let workbook = calamine::open_workbook_auto_from_rs(std::io::Cursor::new(input))
+    .map_err(|err| anyhow::anyhow!(err))?;
+  workbook_to_json(workbook, options)
+}
+
+fn workbook_to_json<RS: std::io::Read + std::io::Seek>(
+  mut workbook: Sheets<RS>,
+  options: Option<&Value>,
+) -> Result<Value> {
   // first row is header
   let has_header = options
     .and_then(|o| o.get("has_header"))
@@ -17,19 +32,33 @@ pub(crate) fn to_json(input: String, options: Option<&Value>) -> Result<Value> {
     .and_then(|v| v.as_array())
     .map(|a| a.iter().map(|v| v.to_string()).collect::<Vec<String>>());
 
-  let sheet = options
-    .and_then(|o| o.get("sheet"))
-    .and_then(|v| v.as_str())
-    .unwrap_or("Sheet1");
+  // the rows are arrays, the header is the first row if has_header
+  let as_object = options
+    .and_then(|o| o.get("as_object"))
+    .and_then(|v| v.as_bool())
+    .unwrap_or(true);
 
-  let mut workbook =
-    calamine::open_workbook_auto(input.clone()).map_err(|err| anyhow::anyhow!(err))?;
+  // the sheet name, or the index of the sheet, eg: 0 for the first sheet
+  let sheet = match options.and_then(|o| o.get("sheet")) {
+    Some(Value::String(sheet)) => sheet.to_string(),
+    Some(Value::Number(index)) => {
+      let index = index.as_u64().unwrap_or_default() as usize;
+      workbook
+        .sheet_names()
+        .get(index)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Sheet {} is not found", index))?
+    }
+    _ => "Sheet1".to_string(),
+  };
 
   let range = workbook
-    .worksheet_range(sheet)
+    .worksheet_range(&sheet)
     .map_err(|err| anyhow::anyhow!(err))?;
 
-  if has_header || headers.is_some() {
+  if !as_object {
+    to_json_array(&range)
+  } else if has_header || headers.is_some() {
     to_json_object(range, has_header, headers)
   } else {
     to_json_map(&range)
@@ -59,6 +88,15 @@ fn excel_value(value: &Data) -> Value {
   }
 }
 
+fn to_json_array(range: &Range<Data>) -> Result<Value> {
+  Ok(Value::Array(
+    range
+      .rows()
+      .map(|r| Value::Array(r.iter().map(excel_value).collect()))
+      .collect(),
+  ))
+}
+
 fn to_json_map(range: &Range<Data>) -> Result<Value> {
   let mut map = serde_json::Map::new();
   range.rows().enumerate().for_each(|(i, r)| {
@@ -21,7 +21,8 @@
 //! - options:
 //!   - has_header: bool, default true, if true, first row will be used as header
 //!   - headers: array of string, if provided, will be used as header
-//!   - sheet: string, default "Sheet1", sheet name to be used
+//!   - sheet: string or index (0 is the first sheet), default "Sheet1", the sheet to be used
+//!   - as_object: bool, default true, if false, each row will be converted to array, including the header row
 //!
 mod config_loader;
 mod csv;
@@ -94,16 +95,12 @@ pub fn bytes_to_json<S: AsRef<str>>(
   let mimetype = mimetype.as_ref();
   match mimetype {
     // pass all text based mime type to to_json
-    "text/csv"
-    | "application/json"
-    | "application/ndjson"
-    | "text/ini"
-    | "text/yaml"
-    | "text/plain"
-    | "application/yaml"
-    | "application/x-yaml"
-    | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
-    | "application/vnd.ms-excel" => to_json(try_to_utf8(input.into())?, mimetype, conf),
+    "text/csv" | "application/json" | "application/ndjson" | "text/ini" | "text/yaml"
+    | "text/plain" | "application/yaml" | "application/x-yaml" => {
+      to_json(try_to_utf8(input.into())?, mimetype, conf)
+    }
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    | "application/vnd.ms-excel" => excel::bytes_to_json(input, conf),
     // else convert to bytes
     _ => {
       let conf = json!({
@@ -0,0 +1,27 @@
+use a2a_tojson::bytes_to_json;
+use rust_xlsxwriter::Workbook;
+use serde_json::json;
+
+const XLSX: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+#[test]
+fn test_excel_sheet() {
+  let mut workbook = Workbook::new();
+  for name in ["Data", "Sheet1"] {
+    let sheet = workbook.add_worksheet().set_name(name).unwrap();
+    sheet.write_string(0, 0, "name").unwrap();
+    sheet.write_string(1, 0, name).unwrap();
+  }
+  let input = bytes::Bytes::from(workbook.save_to_buffer().unwrap());
+
+  // "Sheet1" by default
+  let value = bytes_to_json(input.clone(), XLSX, None).unwrap();
+  assert_eq!(value, json!([{"name": "Sheet1"}]));
+
+  // by the name or the index
+  let value = bytes_to_json(input.clone(), XLSX, Some(&json!({"sheet": "Data"}))).unwrap();
+  assert_eq!(value, json!([{"name": "Data"}]));
+  let value = bytes_to_json(input.clone(), XLSX, Some(&json!({"sheet": 0}))).unwrap();
+  assert_eq!(value, json!([{"name": "Data"}]));
+  assert!(bytes_to_json(input, XLSX, Some(&json!({"sheet": 2}))).is_err());
+}
@@ -56,9 +56,31 @@ pub struct SqlAction {
   pub override_result_mimetype: Option<String>,
 
   // sql fields
+  #[serde(default)]
   pub query: String,
   pub rows: Option<Value>,
   pub connection: String,
+  // multiple statements to run in order, in a transaction unless `transaction` is false
+  pub statements: Option<Vec<SqlStatement>>,
+  // run in a transaction, rollback all on error
+  pub transaction: Option<bool>,
+  // format of the datetime columns: epoch(default, milliseconds) or iso
+  pub date_format: Option<String>,
+  // query(default), or schema to list the tables and columns
+  pub method: Option<String>,
+  // stream the rows to a file instead of returning them: the path, or {path, format, connection}
+  pub save_to: Option<Value>,
+  // how to load the batch rows of INSERT: values(default, multi-row VALUES), copy(postgres COPY) or off
+  pub bulk: Option<String>,
+  // insert or update on conflict: {keys, update}
+  pub upsert: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlStatement {
+  pub query: String,
+  pub rows: Option<Value>,
 }
 
 pub type SqlActionResult = Value;
@@ -71,16 +93,39 @@ pub struct EMailAction {
 
   // email fields
 
-  // method: SEND, RECV, DELETE
+  // method: RECV, SEND, FLAG, UNFLAG, MOVE, COPY, DELETE, EXPUNGE, FOLDERS
   pub method: String,
   // account configuration
   pub account: Value,
+  // the local mbox file or Maildir directory to recv from instead of the imap of the account
+  pub source: Option<String>,
   // folder to use for this request, otherwise use the default folder
   pub folder: Option<String>,
   // message to send/delete
   pub message: Option<Value>,
-  // lastId to recv
+  // lastId to recv, `last_id` is also accepted
+  #[serde(alias = "last_id")]
   pub last_id: Option<u64>,
+  // the search filters to recv: {from, to, subject, body, since, before, unseen, flagged, hasAttachment}
+  pub filter: Option<Value>,
+  // the max messages to recv, default is 30
+  pub limit: Option<usize>,
+  // the sort order to recv, eg: "date desc"(default), "arrival", "from asc, date desc"
+  pub sort: Option<String>,
+  // the ids returned by recv to FLAG, UNFLAG, MOVE, COPY or DELETE
+  pub ids: Option<Value>,
+  // the flags to FLAG or UNFLAG, eg: seen, flagged, answered
+  pub flags: Option<Vec<String>>,
+  // the folder to MOVE, COPY or DELETE(eg: the trash) to
+  pub target: Option<String>,
+  // the raw headers to include in the received messages, eg: List-Id, X-Priority
+  pub headers: Option<Vec<String>>,
+  // the dir to save the received attachments, a local path or any opendal url, default is the temp dir
+  pub attachment_dir: Option<String>,
+  // the connection options of the attachment dir, eg: the bucket and credentials of s3
+  pub attachment_connection: Option<Value>,
+  // remove the saved attachments older than the days after recv, default is to keep them
+  pub attachment_retention_days: Option<u64>,
 }
 
 // EMailActionResult is a array of Message
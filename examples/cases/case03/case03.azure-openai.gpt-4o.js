@@ -15,16 +15,16 @@ export async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: config.email_account,
-    last_id: lastId
+    lastId
   });
 
-  const newEmails = emails.filter(email => email.from === 'tom@vendor.com' && email.attachments && email.attachments.length > 0);
+  const newEmails = emails.filter(email => email.from?.address === 'tom@vendor.com' && email.attachments && email.attachments.length > 0);
 
   // 初始化通知明细
   let notificationDetails = [];
 
   for (const email of newEmails) {
-    for (const attachment of email.attachments) {
+    for (const { path: attachment } of email.attachments) {
       // 检查文件类型
       if (attachment.endsWith('.png') || attachment.endsWith('.jpg') || attachment.endsWith('.jpeg') || attachment.endsWith('.pdf')) {
         
@@ -95,7 +95,7 @@ export async function main(config, params) {
 
   // 发送钉钉通知
   if (notificationDetails.length > 0) {
-    const vendor = new URL(emails[0].from).hostname;
+    const vendor = emails[0].from.address.split('@')[1];
     const todayDate = new Date().toLocaleDateString();
 
     const notificationContent = `
@@ -10,8 +10,8 @@ async function main(config, params) {
 
     // 3. 处理新邮件
     for (const email of newEmails) {
-        if (email.from === 'tom@vendor.com' && email.attachments.length > 0) {
-            const fileData = await attachFileToOCR(email.attachments[0], ocrUrl, ocrApiKey);
+        if (email.from?.address === 'tom@vendor.com' && email.attachments.length > 0) {
+            const fileData = await attachFileToOCR(email.attachments[0].path, ocrUrl, ocrApiKey);
             const result = await ocr(fileData);
 
             if (result.documentType === 'invoice') {
@@ -27,7 +27,7 @@ async function main(config, params) {
 
     // 5. 发送通知
     await sendNotification(dingtalk, {
-        vendor: email.from,
+        vendor: email.from.address,
         date: new Date().toLocaleString(),
         documentType: result.documentType,
         sn: result.invoiceNumber || result.receiptNumber,
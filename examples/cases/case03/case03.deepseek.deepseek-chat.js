@@ -15,15 +15,15 @@ export async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: config.email_account,
-    last_id: lastId
+    lastId
   };
   const emails = await doAction(emailAction);
 
   // 处理新邮件
   const ocrResults = [];
   for (const email of emails) {
-    if (email.from.includes('tom@vendor.com') && email.attachments.length > 0) {
-      for (const attachment of email.attachments) {
+    if (email.from?.address === 'tom@vendor.com' && email.attachments.length > 0) {
+      for (const { path: attachment } of email.attachments) {
         const fileAction = {
           kind: "file",
           method: "READ",
@@ -96,7 +96,7 @@ export async function main(config, params) {
   const notifyAction = {
     kind: "notify",
     url: config.dingtalk,
-    message: `# ${emails[0].from.split('@')[1]} 新邮件处理完成\n\n# 日期: ${new Date().toISOString().split('T')[0]}\n\n# 明细\n${notifications.join('\n')}`,
+    message: `# ${emails[0].from.address.split('@')[1]} 新邮件处理完成\n\n# 日期: ${new Date().toISOString().split('T')[0]}\n\n# 明细\n${notifications.join('\n')}`,
     title: "新邮件处理完成"
   };
   return await doAction(notifyAction);
@@ -13,14 +13,14 @@ export async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: config.email_account,
-    last_id: lastId,
+    lastId,
   };
   const emails = await doAction(emailAction);
 
   // 对获取到的文件，如果其发件人是 'tom@microsoft.com', 并且有附件，则将附件中的图片或 PDF 提交给 `OCR` 服务
   for (const email of emails) {
-    if (email.from === 'tom@microsoft.com' && email.attachments.length > 0) {
-      for (const attachment of email.attachments) {
+    if (email.from?.address === 'tom@microsoft.com' && email.attachments.length > 0) {
+      for (const { path: attachment } of email.attachments) {
         const fileAction = {
           kind: "file",
           method: "READ",
@@ -24,7 +24,7 @@ async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: emailAccount,
-    last_id: lastId
+    lastId
   });
   
   // 存储处理结果
@@ -38,9 +38,9 @@ async function main(config, params) {
     }
     
     // 检查发件人是否为tom@vendor.com
-    if (email.from.includes("tom@vendor.com") && email.attachments && email.attachments.length > 0) {
+    if (email.from?.address === "tom@vendor.com" && email.attachments && email.attachments.length > 0) {
       // 处理附件
-      for (const attachmentPath of email.attachments) {
+      for (const { path: attachmentPath } of email.attachments) {
         // 检查附件类型（图像或PDF）
         const fileName = attachmentPath.split('/').pop();
         const ext = fileName.split('.').pop().toLowerCase();
@@ -84,7 +84,7 @@ async function main(config, params) {
           // 保存结果
           if (ocrResult && ocrResult.documentType) {
             results.push({
-              vendor: email.from.split('@')[1],
+              vendor: email.from.address.split('@')[1],
               documentType: ocrResult.documentType,
               sn: ocrResult.invoiceNumber || ocrResult.receiptNumber,
               amount: ocrResult.amount,
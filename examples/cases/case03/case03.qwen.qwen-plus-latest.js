@@ -13,7 +13,7 @@ async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: config.email_account,
-    last_id: lastId
+    lastId
   });
 
   // 用于存储通知详情的数组
@@ -22,9 +22,9 @@ async function main(config, params) {
   // 处理每封邮件
   for (const email of emails) {
     // 检查是否为指定发件人且包含附件
-    if (email.from === 'tom@vendor.com' && email.attachments && email.attachments.length > 0) {
+    if (email.from?.address === 'tom@vendor.com' && email.attachments && email.attachments.length > 0) {
       // 处理每个附件
-      for (const attachmentPath of email.attachments) {
+      for (const { path: attachmentPath } of email.attachments) {
         // 读取附件内容
         const attachmentData = await doAction({
           kind: "file",
@@ -13,7 +13,7 @@ async function main(config, params) {
     kind: 'email',
     method: 'RECV',
     account: config.email_account,
-    last_id: lastId
+    lastId
   });
 
   let newLastId = lastId;
@@ -27,8 +27,8 @@ async function main(config, params) {
     }
 
     // Check if the email is from tom@vendor.com and has attachments
-    if (email.from === 'tom@vendor.com' && email.attachments.length > 0) {
-      for (const attachmentPath of email.attachments) {
+    if (email.from?.address === 'tom@vendor.com' && email.attachments.length > 0) {
+      for (const { path: attachmentPath } of email.attachments) {
         // Read the attachment content
         const attachment = await doAction({
           kind: 'file',
@@ -73,7 +73,7 @@ async function main(config, params) {
             });
 
             // Collect details for notification
-            const vendor = email.from.split('@')[1];
+            const vendor = email.from.address.split('@')[1];
             const date = new Date().toISOString().split('T')[0];
             const sn = docType === 'invoice' ? ocrResult.body.invoiceNumber : ocrResult.body.receiptNumber;
             const amount = ocrResult.body.amount;
@@ -11,15 +11,15 @@ export async function main(config, params) {
     kind: "email",
     method: "RECV",
     account: config.email_account,
-    last_id: lastId,
+    lastId,
   });
 
   // 遍历邮件
   for (const email of emails) {
     // 如果邮件发件人是 'tom@microsoft.com' 并且有附件
-    if (email.from === "tom@microsoft.com" && email.attachments.length > 0) {
+    if (email.from?.address === "tom@microsoft.com" && email.attachments.length > 0) {
       // 遍历附件
-      for (const attachment of email.attachments) {
+      for (const { path: attachment } of email.attachments) {
         // 如果附件是图片或 PDF
         if (attachment.endsWith(".jpg") || attachment.endsWith(".png") || attachment.endsWith(".pdf")) {
           // 将附件内容转为 DataURL
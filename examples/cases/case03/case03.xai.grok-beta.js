@@ -16,7 +16,7 @@ export async function main(config, params) {
     method: "RECV",
     account: config.email_account,
     folder: "INBOX",
-    last_id: lastId
+    lastId
   };
   let emails = await doAction(emailAction);
 
@@ -24,8 +24,8 @@ export async function main(config, params) {
   let newLastId = lastId;
 
   for (let email of emails) {
-    if (email.from === "tom@vendor.com" && email.attachments.length > 0) {
-      for (let attachment of email.attachments) {
+    if (email.from?.address === "tom@vendor.com" && email.attachments.length > 0) {
+      for (let { path: attachment } of email.attachments) {
         // 读取附件内容并准备 OCR 请求
         let fileReadAction = {
           kind: "file",